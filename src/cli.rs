@@ -0,0 +1,240 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::log::info;
+use bevy::prelude::App;
+
+use crate::game::{board, custom, grid, replay};
+use crate::game::{Difficulty, NextLayout, NextPosition, Seed};
+
+pub const USAGE: &str = "\
+usage: minesweeper [options]
+
+options:
+  --difficulty <name>       beginner, intermediate or expert
+  --custom <COLSxROWS:MINES> custom difficulty, e.g. 30x20:120
+  --seed <number>           seed of the first game
+  --board <file>            play the mines of an MBF board file
+  --tile-size <pixels>      size of a tile on screen, 8 to 96
+  --replay <file>           play back a replay file, also .rmv or .avf
+  --position <file|code>    play on from a position file or share code
+  -h, --help                print this message";
+
+/// Options given on the command line, anything left out keeps its default.
+/// Files are loaded while parsing, so problems show up before any window.
+#[derive(Default)]
+pub(crate) struct Options {
+    pub help: bool,
+    pub difficulty: Option<Difficulty>,
+    pub seed: Option<u64>,
+    pub board: Option<board::Layout>,
+    pub tile_size: Option<u32>,
+    pub replay: Option<(replay::Replay, replay::ReplayInfo)>,
+    pub position: Option<board::Board>,
+}
+
+impl Options {
+    pub fn parse<I: Iterator<Item=String>>(mut args: I) -> Result<Self, CliError> {
+        let mut options = Self::default();
+        let mut board = None;
+        let mut replay = None;
+        let mut position = None;
+        while let Some(arg) = args.next() {
+            let option = match arg.as_str() {
+                "-h" | "--help" => {
+                    options.help = true;
+                    continue;
+                }
+                "--difficulty" => "--difficulty",
+                "--custom" => "--custom",
+                "--seed" => "--seed",
+                "--board" => "--board",
+                "--tile-size" => "--tile-size",
+                "--replay" => "--replay",
+                "--position" => "--position",
+                _ => { return Err(CliError::UnknownOption(arg)); }
+            };
+            let value = args.next().ok_or(CliError::MissingValue(option))?;
+
+            match option {
+                "--difficulty" | "--custom" => {
+                    if options.difficulty.is_some() {
+                        return Err(CliError::Conflict("--difficulty", "--custom"));
+                    }
+                    options.difficulty = Some(if option == "--custom" {
+                        parse_custom(&value)?
+                    } else {
+                        parse_difficulty(&value)?
+                    });
+                }
+                "--seed" => {
+                    options.seed = Some(value.parse().map_err(|_| invalid(option, &value, "not a number"))?);
+                }
+                "--board" => { board = Some(PathBuf::from(value)); }
+                "--tile-size" => {
+                    let size = value.parse::<u32>().map_err(|_| invalid(option, &value, "not a number"))?;
+                    if !(grid::TileSize::MIN..=grid::TileSize::MAX).contains(&size) {
+                        let range = format!("goes from {} to {}", grid::TileSize::MIN, grid::TileSize::MAX);
+                        return Err(invalid(option, &value, range));
+                    }
+                    options.tile_size = Some(size);
+                }
+                "--replay" => { replay = Some(PathBuf::from(value)); }
+                _ => { position = Some(value); }
+            }
+        }
+
+        if options.help {
+            return Ok(options);
+        }
+
+        // a board brings its own size and mines
+        if let Some(path) = board {
+            if options.difficulty.is_some() {
+                return Err(CliError::Conflict("--board", "--difficulty/--custom"));
+            }
+            if options.seed.is_some() {
+                return Err(CliError::Conflict("--board", "--seed"));
+            }
+
+            let bytes = fs::read(&path).map_err(|err| CliError::Board(path.clone(), err.to_string()))?;
+            let layout = board::read_mbf(&bytes).map_err(|err| CliError::Board(path.clone(), err.to_string()))?;
+            options.board = Some(layout);
+        }
+
+        // so does a replay
+        if let Some(path) = replay {
+            if options.board.is_some() {
+                return Err(CliError::Conflict("--replay", "--board"));
+            }
+            if options.difficulty.is_some() {
+                return Err(CliError::Conflict("--replay", "--difficulty/--custom"));
+            }
+            if options.seed.is_some() {
+                return Err(CliError::Conflict("--replay", "--seed"));
+            }
+
+            let (loaded, info) = replay::Replay::load(&path).map_err(|err| CliError::Replay(path.clone(), err.to_string()))?;
+            loaded.check().map_err(|err| CliError::Replay(path.clone(), err.to_string()))?;
+            options.replay = Some((loaded, info));
+        }
+
+        // and a position, from a file or else a share code
+        if let Some(value) = position {
+            if options.board.is_some() || options.replay.is_some() {
+                return Err(CliError::Conflict("--position", "--board/--replay"));
+            }
+            if options.difficulty.is_some() {
+                return Err(CliError::Conflict("--position", "--difficulty/--custom"));
+            }
+            if options.seed.is_some() {
+                return Err(CliError::Conflict("--position", "--seed"));
+            }
+
+            let loaded = if Path::new(&value).is_file() {
+                fs::read_to_string(&value)
+                    .map_err(|err| err.to_string())
+                    .and_then(|text| board::read_notation(&text).map_err(|err| err.to_string()))
+            } else {
+                board::read_share_code(&value).map_err(|err| err.to_string())
+            };
+            options.position = Some(loaded.map_err(|err| CliError::Position(value, err))?);
+        }
+        Ok(options)
+    }
+
+    /// Set up the game's resources, after the game plugin is added.
+    pub fn apply(self, app: &mut App) {
+        if let Some(difficulty) = self.difficulty {
+            app.insert_resource(difficulty);
+        }
+        if let Some(size) = self.tile_size {
+            app.insert_resource(grid::TileSize::new(size as f32));
+        }
+        if let Some(seed) = self.seed {
+            app.world.resource_mut::<Seed>().set_next(seed);
+        }
+        if let Some(layout) = self.board {
+            app.insert_resource(NextLayout(Some(layout)));
+        }
+        if let Some((replay, info)) = self.replay {
+            info!("play replay: {}", info);
+            // checked while parsing
+            let _ = app.world.resource_mut::<replay::Playback>().start(replay);
+        }
+        if let Some(position) = self.position {
+            app.insert_resource(NextPosition(Some(position)));
+        }
+    }
+}
+
+fn parse_difficulty(value: &str) -> Result<Difficulty, CliError> {
+    match value {
+        "beginner" => Ok(Difficulty::Beginner),
+        "intermediate" => Ok(Difficulty::Intermediate),
+        "expert" => Ok(Difficulty::Expert),
+        _ => Err(invalid("--difficulty", value, "use beginner, intermediate or expert")),
+    }
+}
+
+// COLSxROWS:MINES
+fn parse_custom(value: &str) -> Result<Difficulty, CliError> {
+    let format = || invalid("--custom", value, "use COLSxROWS:MINES");
+    let (size, mines) = value.split_once(':').ok_or_else(format)?;
+    let (columns, rows) = size.split_once('x').ok_or_else(format)?;
+    let columns = columns.parse::<usize>().map_err(|_| format())?;
+    let rows = rows.parse::<usize>().map_err(|_| format())?;
+    let mines = mines.parse::<usize>().map_err(|_| format())?;
+
+    let limits = custom::CustomLimits::unbounded();
+    let difficulty = Difficulty::Custom {
+        size: grid::GridSize::new(columns, rows),
+        mines,
+    };
+    limits.check_columns(columns)
+        .and_then(|_| limits.check_rows(rows))
+        .and_then(|_| difficulty.validate(board::FirstClick::default()))
+        .map_err(|err| invalid("--custom", value, err.to_string()))?;
+    Ok(difficulty)
+}
+
+#[inline]
+fn invalid<S: Into<String>>(option: &'static str, value: &str, reason: S) -> CliError {
+    CliError::InvalidValue {
+        option,
+        value: value.to_string(),
+        reason: reason.into(),
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum CliError {
+    UnknownOption(String),
+    MissingValue(&'static str),
+    InvalidValue {
+        option: &'static str,
+        value: String,
+        reason: String,
+    },
+    Conflict(&'static str, &'static str),
+    Board(PathBuf, String),
+    Replay(PathBuf, String),
+    Position(String, String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::UnknownOption(arg) => write!(f, "unknown option {}", arg),
+            CliError::MissingValue(option) => write!(f, "{} needs a value", option),
+            CliError::InvalidValue { option, value, reason } => {
+                write!(f, "invalid {} {}: {}", option, value, reason)
+            }
+            CliError::Conflict(a, b) => write!(f, "{} can't be combined with {}", a, b),
+            CliError::Board(path, reason) => write!(f, "can't load board {}: {}", path.display(), reason),
+            CliError::Replay(path, reason) => write!(f, "can't play replay {}: {}", path.display(), reason),
+            CliError::Position(value, reason) => write!(f, "can't load position {}: {}", value, reason),
+        }
+    }
+}
@@ -0,0 +1,462 @@
+use crate::game::grid::GridSize;
+use super::*;
+
+pub(crate) enum RevealResult {
+    // cell is flagged or already revealed
+    None,
+    // amount of cells that are revealed
+    Revealed(usize),
+    // cell is a mine, game over
+    Exploded,
+}
+
+/// Resource
+///
+/// The actual state of a game: where the mines are and which cells are
+/// revealed or flagged. Tiles on the grid only mirror this state.
+#[derive(Clone)]
+pub(crate) struct Board {
+    size: GridSize,
+    mines: usize,
+    cells: Vec<Cell>,
+    placed: bool,
+    flags: usize,
+    revealed: usize,
+    exploded: Option<usize>,
+}
+
+impl Board {
+    pub fn new(size: GridSize, mines: usize) -> Self {
+        Self {
+            size,
+            mines,
+            cells: vec![Cell::default(); size.capacity()],
+            placed: false,
+            flags: 0,
+            revealed: 0,
+            exploded: None,
+        }
+    }
+
+    /// Board with the mines of the layout already placed.
+    pub fn with_layout(layout: &Layout) -> Self {
+        let mut board = Self::new(layout.size(), layout.mines().len());
+        board.place_mines(layout.mines());
+        board
+    }
+
+    #[inline(always)]
+    pub fn size(&self) -> GridSize { self.size }
+
+    #[inline(always)]
+    pub fn mines(&self) -> usize { self.mines }
+
+    #[inline(always)]
+    pub fn flags(&self) -> usize { self.flags }
+
+    #[inline(always)]
+    pub fn revealed(&self) -> usize { self.revealed }
+
+    /// Whether mines have been placed on the board.
+    #[inline(always)]
+    pub fn is_placed(&self) -> bool { self.placed }
+
+    /// Whether every cell without a mine is revealed.
+    #[inline]
+    pub fn is_cleared(&self) -> bool {
+        self.placed && self.exploded.is_none() && self.revealed == self.cells.len() - self.mines
+    }
+
+    #[inline(always)]
+    pub fn is_lost(&self) -> bool { self.exploded.is_some() }
+
+    /// Index of the mine that went off, if any.
+    #[inline(always)]
+    pub fn exploded(&self) -> Option<usize> { self.exploded }
+
+    #[inline(always)]
+    pub fn cell_at(&self, index: usize) -> &Cell { &self.cells[index] }
+
+    #[inline(always)]
+    pub fn cells(&self) -> &[Cell] { &self.cells }
+
+    /// Place mines at the given indexes and count the adjacent mines of each
+    /// cell. Any previously placed mines are removed.
+    pub fn place_mines(&mut self, indexes: &[usize]) {
+        for cell in self.cells.iter_mut() {
+            cell.set_mine(false);
+        }
+        for &index in indexes {
+            self.cells[index].set_mine(true);
+        }
+
+        for index in 0..self.cells.len() {
+            let adjacent = self.size
+                .neighbours(index)
+                .filter(|&i| self.cells[i].is_mine())
+                .count();
+            self.cells[index].set_adjacent(adjacent as u8);
+        }
+
+        self.mines = indexes.len();
+        self.placed = true;
+    }
+
+    /// Place mines where the generator wants them, knowing the first cell
+    /// to reveal is at index.
+    pub fn generate(&mut self, generator: &dyn MineGenerator, index: usize, first_click: FirstClick, rng: &mut BoardRng) {
+        let mines = self.mines.min(self.cells.len());
+        let indexes = generator.generate(self.size, mines, index, first_click, rng);
+        self.place_mines(&indexes);
+    }
+
+    /// The board's 3BV: the least amount of left clicks that clear it. Each
+    /// opening counts once, so does each number that doesn't border one.
+    #[inline]
+    pub fn bbbv(&self) -> usize {
+        self.count_bbbv(false)
+    }
+
+    /// The part of the 3BV that is revealed so far.
+    #[inline]
+    pub fn solved_bbbv(&self) -> usize {
+        self.count_bbbv(true)
+    }
+
+    fn count_bbbv(&self, only_revealed: bool) -> usize {
+        let mut counted = vec![false; self.cells.len()];
+        let mut clicks = 0;
+        for index in 0..self.cells.len() {
+            let cell = self.cells[index];
+            if counted[index] || cell.is_mine() || cell.adjacent() != 0 {
+                continue;
+            }
+
+            // revealing any cell of an opening reveals all of it
+            if !only_revealed || cell.is_revealed() {
+                clicks += 1;
+            }
+            let mut stack = vec![index];
+            while let Some(index) = stack.pop() {
+                if counted[index] {
+                    continue;
+                }
+                counted[index] = true;
+                if self.cells[index].adjacent() == 0 {
+                    stack.extend(self.size
+                        .neighbours(index)
+                        .filter(|&i| !counted[i] && !self.cells[i].is_mine()));
+                }
+            }
+        }
+
+        clicks + self.cells
+            .iter()
+            .enumerate()
+            .filter(|(i, cell)| !counted[*i] && !cell.is_mine())
+            .filter(|(_, cell)| !only_revealed || cell.is_revealed())
+            .count()
+    }
+
+    /// Cycle between hidden, flagged and, when enabled, a question mark.
+    /// Returns false when the cell is already revealed.
+    pub fn cycle_mark(&mut self, index: usize, question_marks: bool) -> bool {
+        let cell = &mut self.cells[index];
+        match cell.state() {
+            CellState::Hidden => {
+                cell.set_state(CellState::Flagged);
+                self.flags += 1;
+            }
+            CellState::Flagged => {
+                if question_marks {
+                    cell.set_state(CellState::Question);
+                } else {
+                    cell.set_state(CellState::Hidden);
+                }
+                self.flags -= 1;
+            }
+            CellState::Question => {
+                cell.set_state(CellState::Hidden);
+            }
+            CellState::Revealed => { return false; }
+        }
+        true
+    }
+
+    /// Reveal the cell at index. When it has no adjacent mines, the whole
+    /// connected empty region and its numbered border are revealed as well.
+    pub fn reveal(&mut self, index: usize) -> RevealResult {
+        if !self.cells[index].is_covered() {
+            return RevealResult::None;
+        }
+        if self.cells[index].is_mine() {
+            self.reveal_cell(index);
+            self.exploded.get_or_insert(index);
+            return RevealResult::Exploded;
+        }
+
+        let before = self.revealed;
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            if !self.cells[index].is_covered() {
+                continue;
+            }
+
+            self.reveal_cell(index);
+            if self.cells[index].adjacent() == 0 {
+                stack.extend(self.size
+                    .neighbours(index)
+                    .filter(|&i| self.cells[i].is_covered()));
+            }
+        }
+
+        RevealResult::Revealed(self.revealed - before)
+    }
+
+    /// Reveal all hidden neighbours of a revealed number, when the amount of
+    /// flags around it matches that number. A wrongly placed flag means one
+    /// of the revealed neighbours is a mine.
+    pub fn chord(&mut self, index: usize) -> RevealResult {
+        let cell = self.cells[index];
+        if !cell.is_revealed() || cell.is_mine() || cell.adjacent() == 0 {
+            return RevealResult::None;
+        }
+
+        let flags = self.size
+            .neighbours(index)
+            .filter(|&i| self.cells[i].is_flagged())
+            .count();
+        if flags != cell.adjacent() as usize {
+            return RevealResult::None;
+        }
+
+        // question marks don't count as flags, so they're revealed as well
+        let hidden = self.size
+            .neighbours(index)
+            .filter(|&i| self.cells[i].is_covered())
+            .collect::<Vec<_>>();
+
+        let mut revealed = 0;
+        let mut exploded = false;
+        for i in hidden {
+            match self.reveal(i) {
+                RevealResult::Revealed(n) => { revealed += n; }
+                RevealResult::Exploded => { exploded = true; }
+                RevealResult::None => {}
+            }
+        }
+
+        if exploded {
+            return RevealResult::Exploded;
+        }
+        if revealed == 0 {
+            return RevealResult::None;
+        }
+        RevealResult::Revealed(revealed)
+    }
+
+    /// Flag all mines that are still hidden.
+    pub fn flag_mines(&mut self) {
+        for cell in self.cells.iter_mut() {
+            if cell.is_mine() && cell.is_covered() {
+                cell.set_state(CellState::Flagged);
+                self.flags += 1;
+            }
+        }
+    }
+
+    /// Reveal all mines that are still hidden, flagged mines stay flagged.
+    pub fn reveal_mines(&mut self) {
+        for index in 0..self.cells.len() {
+            let cell = self.cells[index];
+            if cell.is_mine() && cell.is_covered() {
+                self.reveal_cell(index);
+            }
+        }
+    }
+
+    #[inline]
+    fn reveal_cell(&mut self, index: usize) {
+        let cell = &mut self.cells[index];
+        if cell.is_flagged() {
+            self.flags -= 1;
+        }
+        if !cell.is_revealed() {
+            cell.set_state(CellState::Revealed);
+            self.revealed += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 4x4 board with mines at the given columns and rows
+    fn board(mines: &[(usize, usize)]) -> Board {
+        sized(GridSize::new(4, 4), mines)
+    }
+
+    fn sized(size: GridSize, mines: &[(usize, usize)]) -> Board {
+        let mut board = Board::new(size, mines.len());
+        let indexes = mines.iter().map(|&(col, row)| size.index_of(col, row)).collect::<Vec<_>>();
+        board.place_mines(&indexes);
+        board
+    }
+
+    #[test]
+    fn adjacent_counts() {
+        let board = board(&[(0, 0), (2, 0)]);
+        let size = board.size();
+        assert!(board.is_placed());
+        assert_eq!(board.mines(), 2);
+        assert_eq!(board.cell_at(size.index_of(1, 0)).adjacent(), 2);
+        assert_eq!(board.cell_at(size.index_of(1, 1)).adjacent(), 2);
+        assert_eq!(board.cell_at(size.index_of(0, 1)).adjacent(), 1);
+        assert_eq!(board.cell_at(size.index_of(3, 3)).adjacent(), 0);
+        assert_eq!(board.cells().iter().filter(|cell| cell.is_mine()).count(), 2);
+    }
+
+    #[test]
+    fn place_mines_replaces_layout() {
+        let mut board = board(&[(0, 0)]);
+        board.place_mines(&[15]);
+        assert!(!board.cell_at(0).is_mine());
+        assert!(board.cell_at(15).is_mine());
+        assert_eq!(board.cell_at(1).adjacent(), 0);
+    }
+
+    #[test]
+    fn cycle_mark_with_question_marks() {
+        let mut board = board(&[(0, 0)]);
+        assert!(board.cycle_mark(5, true));
+        assert!(board.cycle_mark(5, true));
+        assert!(board.cell_at(5).is_question());
+        assert_eq!(board.flags(), 0);
+
+        // a question mark doesn't stop a reveal
+        assert!(matches!(board.reveal(5), RevealResult::Revealed(1)));
+        assert!(!board.cycle_mark(5, true));
+    }
+
+    #[test]
+    fn cycle_mark() {
+        let mut board = board(&[(0, 0)]);
+        assert!(board.cycle_mark(5, false));
+        assert!(board.cell_at(5).is_flagged());
+        assert_eq!(board.flags(), 1);
+        assert!(board.cycle_mark(5, false));
+        assert!(board.cell_at(5).is_hidden());
+        assert_eq!(board.flags(), 0);
+    }
+
+    #[test]
+    fn reveal_number() {
+        let mut board = board(&[(0, 0)]);
+        let index = board.size().index_of(1, 1);
+        assert!(matches!(board.reveal(index), RevealResult::Revealed(1)));
+        assert!(board.cell_at(index).is_revealed());
+        assert_eq!(board.revealed(), 1);
+        assert!(matches!(board.reveal(index), RevealResult::None));
+    }
+
+    #[test]
+    fn reveal_flood_fills_zeros() {
+        let mut board = board(&[(0, 0)]);
+        let index = board.size().index_of(3, 3);
+        // everything but the mine opens up
+        assert!(matches!(board.reveal(index), RevealResult::Revealed(15)));
+        assert!(!board.cell_at(0).is_revealed());
+    }
+
+    #[test]
+    fn cleared_and_flag_mines() {
+        let mut board = board(&[(0, 0)]);
+        assert!(!board.is_cleared());
+        board.reveal(board.size().index_of(3, 3));
+        assert!(board.is_cleared());
+
+        board.flag_mines();
+        assert!(board.cell_at(0).is_flagged());
+        assert_eq!(board.flags(), 1);
+    }
+
+    #[test]
+    fn flags_are_not_revealed() {
+        let mut board = board(&[(0, 0)]);
+        let size = board.size();
+        let flagged = size.index_of(2, 2);
+        board.cycle_mark(flagged, false);
+
+        assert!(matches!(board.reveal(flagged), RevealResult::None));
+        board.reveal(size.index_of(3, 3));
+        assert!(board.cell_at(flagged).is_flagged());
+        assert_eq!(board.flags(), 1);
+    }
+
+    #[test]
+    fn chord_with_right_flags() {
+        let mut board = board(&[(0, 0), (3, 3)]);
+        let size = board.size();
+        let number = size.index_of(1, 1);
+        board.reveal(number);
+        board.cycle_mark(0, false);
+
+        assert!(matches!(board.chord(number), RevealResult::Revealed(_)));
+        for index in size.neighbours(number) {
+            assert!(board.cell_at(index).is_revealed() || index == 0);
+        }
+        assert!(!board.is_lost());
+    }
+
+    #[test]
+    fn chord_with_wrong_flag_count() {
+        let mut board = board(&[(0, 0), (3, 3)]);
+        let size = board.size();
+        let number = size.index_of(1, 1);
+        board.reveal(number);
+
+        // no flags yet
+        assert!(matches!(board.chord(number), RevealResult::None));
+        assert_eq!(board.revealed(), 1);
+
+        // too many flags
+        board.cycle_mark(0, false);
+        board.cycle_mark(size.index_of(1, 0), false);
+        assert!(matches!(board.chord(number), RevealResult::None));
+        assert_eq!(board.revealed(), 1);
+    }
+
+    #[test]
+    fn chord_with_wrong_flag_explodes() {
+        let mut board = board(&[(0, 0), (3, 3)]);
+        let size = board.size();
+        let number = size.index_of(1, 1);
+        board.reveal(number);
+        board.cycle_mark(size.index_of(1, 0), false);
+
+        assert!(matches!(board.chord(number), RevealResult::Exploded));
+        assert!(board.is_lost());
+        assert_eq!(board.exploded(), Some(0));
+    }
+
+    #[test]
+    fn explode_and_reveal_mines() {
+        let mut board = board(&[(0, 0), (3, 3)]);
+        board.cycle_mark(15, false);
+        board.cycle_mark(5, false);
+        assert!(matches!(board.reveal(0), RevealResult::Exploded));
+        assert_eq!(board.exploded(), Some(0));
+        assert!(board.cell_at(0).is_revealed());
+        assert!(!board.cycle_mark(0, false));
+
+        // flagged mines keep their flag
+        board.place_mines(&[0, 10, 15]);
+        board.reveal_mines();
+        assert!(board.cell_at(10).is_revealed());
+        assert!(board.cell_at(15).is_flagged());
+        assert!(board.cell_at(5).is_flagged());
+        assert_eq!(board.flags(), 2);
+        assert_eq!(board.revealed(), 2);
+    }
+}
@@ -0,0 +1,62 @@
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum CellState {
+    Hidden,
+    Flagged,
+    Question,
+    Revealed,
+}
+
+/// A single cell of the board, independent of how it's rendered.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Cell {
+    mine: bool,
+    adjacent: u8,
+    state: CellState,
+}
+
+impl Cell {
+    #[inline(always)]
+    pub fn is_mine(&self) -> bool { self.mine }
+
+    /// Amount of mines in the (up to) 8 surrounding cells.
+    #[inline(always)]
+    pub fn adjacent(&self) -> u8 { self.adjacent }
+
+    #[inline(always)]
+    pub fn state(&self) -> CellState { self.state }
+
+    #[inline(always)]
+    pub fn is_hidden(&self) -> bool { self.state == CellState::Hidden }
+
+    #[inline(always)]
+    pub fn is_flagged(&self) -> bool { self.state == CellState::Flagged }
+
+    #[inline(always)]
+    pub fn is_question(&self) -> bool { self.state == CellState::Question }
+
+    /// Hidden or marked with a question mark, either way it can be revealed.
+    #[inline(always)]
+    pub fn is_covered(&self) -> bool { self.is_hidden() || self.is_question() }
+
+    #[inline(always)]
+    pub fn is_revealed(&self) -> bool { self.state == CellState::Revealed }
+
+    #[inline(always)]
+    pub(super) fn set_mine(&mut self, mine: bool) { self.mine = mine; }
+
+    #[inline(always)]
+    pub(super) fn set_adjacent(&mut self, adjacent: u8) { self.adjacent = adjacent; }
+
+    #[inline(always)]
+    pub(super) fn set_state(&mut self, state: CellState) { self.state = state; }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            mine: false,
+            adjacent: 0,
+            state: CellState::Hidden,
+        }
+    }
+}
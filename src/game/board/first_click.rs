@@ -0,0 +1,77 @@
+use crate::game::grid::GridSize;
+
+/// Resource
+///
+/// What the first revealed cell of a game is guaranteed to be. Mines are
+/// placed once this cell is known.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum FirstClick {
+    // the first cell is never a mine
+    #[default]
+    Safe,
+    // the first cell is always empty, which opens up a region
+    Opening,
+    // a mine under the first cell moves to the top-left corner
+    Classic,
+}
+
+impl FirstClick {
+    #[inline]
+    pub fn next(&self) -> Self {
+        use FirstClick::*;
+        match self {
+            Safe => Opening,
+            Opening => Classic,
+            Classic => Safe,
+        }
+    }
+
+    /// Maximum amount of mines a board of the given size can hold, while
+    /// still keeping the guarantee for any first cell.
+    #[inline]
+    pub fn max_mines(&self, size: GridSize) -> usize {
+        use FirstClick::*;
+        let keep_free = match self {
+            Safe | Classic => 1,
+            // a cell and its neighbours, at most a 3x3 square
+            Opening => size.columns().min(3) * size.rows().min(3),
+        };
+        size.capacity().saturating_sub(keep_free)
+    }
+
+    /// Cells that can't get a mine when the first revealed cell is at start.
+    pub fn excluded(&self, size: GridSize, start: usize) -> Vec<usize> {
+        use FirstClick::*;
+        match self {
+            Safe => vec![start],
+            Opening => {
+                let mut excluded = size.neighbours(start).collect::<Vec<_>>();
+                excluded.push(start);
+                excluded
+            }
+            // mines are moved afterwards
+            Classic => vec![],
+        }
+    }
+
+    /// With the classic guarantee, a mine under the first revealed cell moves
+    /// to the first free cell, scanning from the top-left corner.
+    pub fn relocate(&self, size: GridSize, start: usize, mines: &mut Vec<usize>) {
+        if *self != FirstClick::Classic {
+            return;
+        }
+
+        if let Some(pos) = mines.iter().position(|&i| i == start) {
+            let free = (0..size.rows())
+                .rev()
+                .flat_map(|row| (0..size.columns()).map(move |col| (col, row)))
+                .map(|(col, row)| size.index_of(col, row))
+                .find(|i| *i != start && !mines.contains(i));
+
+            match free {
+                Some(free) => { mines[pos] = free; }
+                None => { mines.swap_remove(pos); }
+            }
+        }
+    }
+}
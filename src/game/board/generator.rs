@@ -0,0 +1,235 @@
+use crate::game::grid::GridSize;
+
+use super::*;
+
+/// Decides where the mines of a new board go.
+pub(crate) trait MineGenerator: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Whether the boards are meant to be solvable without guessing.
+    fn no_guess(&self) -> bool { false }
+
+    /// The first click guarantee the boards keep when the player picked
+    /// chosen, the amount of mines has to fit this one.
+    fn first_click(&self, chosen: FirstClick) -> FirstClick { chosen }
+
+    /// Pick the indexes of the mines. The first cell to reveal is at start,
+    /// the picked mines have to keep the first click guarantee.
+    fn generate(&self, size: GridSize, mines: usize, start: usize, first_click: FirstClick, rng: &mut BoardRng) -> Vec<usize>;
+}
+
+/// All generators that ship with the game.
+pub(crate) fn generators() -> Vec<Box<dyn MineGenerator>> {
+    vec![
+        Box::new(UniformGenerator),
+        Box::new(OpeningGenerator),
+        Box::new(NoGuessGenerator::default()),
+        Box::new(ClusteredGenerator::default()),
+        Box::new(GradientGenerator::default()),
+    ]
+}
+
+#[inline]
+fn candidates(size: GridSize, excluded: &[usize]) -> Vec<usize> {
+    (0..size.capacity())
+        .filter(|i| !excluded.contains(i))
+        .collect()
+}
+
+/// Every cell has the same chance of getting a mine.
+pub(crate) struct UniformGenerator;
+
+impl MineGenerator for UniformGenerator {
+    fn name(&self) -> &'static str { "uniform" }
+
+    fn generate(&self, size: GridSize, mines: usize, start: usize, first_click: FirstClick, rng: &mut BoardRng) -> Vec<usize> {
+        let excluded = first_click.excluded(size, start);
+        let mut indexes = pick(rng, candidates(size, &excluded), mines);
+        first_click.relocate(size, start, &mut indexes);
+        indexes
+    }
+}
+
+/// Uniform, but the first cell is always empty so it opens up a region,
+/// whatever the first click guarantee is.
+pub(crate) struct OpeningGenerator;
+
+impl MineGenerator for OpeningGenerator {
+    fn name(&self) -> &'static str { "safe opening" }
+
+    fn first_click(&self, _: FirstClick) -> FirstClick { FirstClick::Opening }
+
+    fn generate(&self, size: GridSize, mines: usize, start: usize, _: FirstClick, rng: &mut BoardRng) -> Vec<usize> {
+        UniformGenerator.generate(size, mines, start, FirstClick::Opening, rng)
+    }
+}
+
+/// Only boards that can be solved from the first cell without guessing.
+/// Layouts are tried until a solvable one is found or the attempts run out,
+/// in which case the last tried layout is used. With too many mines for an
+/// opening, the layout is uniform.
+pub(crate) struct NoGuessGenerator {
+    pub attempts: usize,
+}
+
+impl Default for NoGuessGenerator {
+    fn default() -> Self { Self { attempts: 1000 } }
+}
+
+impl MineGenerator for NoGuessGenerator {
+    fn name(&self) -> &'static str { "no guess" }
+
+    fn no_guess(&self) -> bool { true }
+
+    fn generate(&self, size: GridSize, mines: usize, start: usize, first_click: FirstClick, rng: &mut BoardRng) -> Vec<usize> {
+        // solving has to start with an opening
+        if mines > FirstClick::Opening.max_mines(size) {
+            return UniformGenerator.generate(size, mines, start, first_click, rng);
+        }
+
+        let excluded = FirstClick::Opening.excluded(size, start);
+        let mut board = Board::new(size, mines);
+        let mut indexes = Vec::new();
+        for _ in 0..self.attempts.max(1) {
+            indexes = pick(rng, candidates(size, &excluded), mines);
+            board.place_mines(&indexes);
+            if is_solvable(&board, start) {
+                return indexes;
+            }
+        }
+        indexes
+    }
+}
+
+/// Mines tend to end up next to each other. The chance of a cell getting a
+/// mine grows with the amount of mines already placed around it.
+pub(crate) struct ClusteredGenerator {
+    // extra weight per adjacent mine, 0 is uniform
+    pub strength: u64,
+}
+
+impl Default for ClusteredGenerator {
+    fn default() -> Self { Self { strength: 4 } }
+}
+
+impl MineGenerator for ClusteredGenerator {
+    fn name(&self) -> &'static str { "clustered" }
+
+    fn generate(&self, size: GridSize, mines: usize, start: usize, first_click: FirstClick, rng: &mut BoardRng) -> Vec<usize> {
+        let excluded = first_click.excluded(size, start);
+        let mut indexes = pick_weighted(rng, candidates(size, &excluded), mines, |index, picked| {
+            let adjacent = size.neighbours(index).filter(|&i| *picked.get(i).unwrap_or(&false)).count() as u64;
+            1 + adjacent * self.strength
+        });
+        first_click.relocate(size, start, &mut indexes);
+        indexes
+    }
+}
+
+/// Mines get denser from the left to the right side of the board.
+pub(crate) struct GradientGenerator {
+    // how many times denser the right side is than the left side
+    pub steepness: u64,
+}
+
+impl Default for GradientGenerator {
+    fn default() -> Self { Self { steepness: 4 } }
+}
+
+impl MineGenerator for GradientGenerator {
+    fn name(&self) -> &'static str { "density gradient" }
+
+    fn generate(&self, size: GridSize, mines: usize, start: usize, first_click: FirstClick, rng: &mut BoardRng) -> Vec<usize> {
+        let excluded = first_click.excluded(size, start);
+        let last = size.columns().saturating_sub(1).max(1) as u64;
+        let mut indexes = pick_weighted(rng, candidates(size, &excluded), mines, |index, _| {
+            let (col, _) = size.position_of(index);
+            // from 1 on the left to steepness on the right, scaled to stay whole
+            last + (col as u64 * (self.steepness.max(1) - 1))
+        });
+        first_click.relocate(size, start, &mut indexes);
+        indexes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(generator: &dyn MineGenerator, size: GridSize, mines: usize, start: usize, first_click: FirstClick, seed: u64) -> Board {
+        let mut board = Board::new(size, mines);
+        board.generate(generator, start, generator.first_click(first_click), &mut seeded_rng(seed));
+        board
+    }
+
+    fn mines(board: &Board) -> Vec<bool> {
+        board.cells().iter().map(|cell| cell.is_mine()).collect()
+    }
+
+    #[test]
+    fn same_seed_same_layout() {
+        let size = GridSize::new(8, 8);
+        let start = size.index_of(3, 3);
+        for generator in generators() {
+            let generator = generator.as_ref();
+            let board = layout(generator, size, 10, start, FirstClick::Safe, 42);
+            assert_eq!(board.cells().iter().filter(|cell| cell.is_mine()).count(), 10, "{}", generator.name());
+            assert!(!board.cell_at(start).is_mine(), "{}", generator.name());
+
+            let same = layout(generator, size, 10, start, FirstClick::Safe, 42);
+            let other = layout(generator, size, 10, start, FirstClick::Safe, 43);
+            assert_eq!(mines(&board), mines(&same), "{}", generator.name());
+            assert_ne!(mines(&board), mines(&other), "{}", generator.name());
+        }
+    }
+
+    #[test]
+    fn first_click_guarantees() {
+        let size = GridSize::new(4, 4);
+        let start = size.index_of(1, 1);
+        for seed in 0..20 {
+            let board = layout(&UniformGenerator, size, 15, start, FirstClick::Safe, seed);
+            assert!(!board.cell_at(start).is_mine());
+
+            let board = layout(&UniformGenerator, size, 7, start, FirstClick::Opening, seed);
+            assert_eq!(board.cell_at(start).adjacent(), 0);
+            assert!(!board.cell_at(start).is_mine());
+
+            let board = layout(&UniformGenerator, size, 15, start, FirstClick::Classic, seed);
+            assert_eq!(board.mines(), 15);
+            assert!(!board.cell_at(start).is_mine());
+
+            // whatever was chosen, this one opens up a region
+            let board = layout(&OpeningGenerator, size, 7, start, FirstClick::Safe, seed);
+            assert_eq!(board.cell_at(start).adjacent(), 0);
+        }
+    }
+
+    #[test]
+    fn no_guess_is_solvable() {
+        let size = GridSize::new(8, 8);
+        let start = size.index_of(3, 3);
+        for seed in 0..5 {
+            let board = layout(&NoGuessGenerator::default(), size, 10, start, FirstClick::Safe, seed);
+            assert!(is_solvable(&board, start));
+        }
+    }
+
+    #[test]
+    fn no_guess_falls_back() {
+        // . . ? ?
+        // . . ? ?
+        // one mine on the right always ends up as a guess
+        let size = GridSize::new(4, 2);
+        let generator = NoGuessGenerator { attempts: 5 };
+        let board = layout(&generator, size, 1, 0, FirstClick::Safe, 42);
+        assert!(!is_solvable(&board, 0));
+        assert!(!board.cell_at(size.index_of(1, 0)).is_mine());
+
+        // too many mines for an opening
+        let size = GridSize::new(3, 2);
+        let board = layout(&generator, size, 5, 0, FirstClick::Safe, 42);
+        assert_eq!(board.cells().iter().filter(|cell| cell.is_mine()).count(), 5);
+        assert!(!board.cell_at(0).is_mine());
+    }
+}
@@ -0,0 +1,97 @@
+use std::fmt;
+
+use crate::game::custom::CustomLimits;
+use crate::game::grid::GridSize;
+use crate::game::DifficultyError;
+
+use super::Board;
+
+/// Where the mines of a board are, independent of any game played on it.
+#[derive(Clone, Debug)]
+pub(crate) struct Layout {
+    size: GridSize,
+    mines: Vec<usize>,
+}
+
+impl Layout {
+    /// Layout with mines at the given indexes, as long as all of them are on
+    /// the board and none of them is there twice. The board is no smaller
+    /// than a custom difficulty may be and has at least one mine.
+    pub fn new(size: GridSize, mut mines: Vec<usize>) -> Result<Self, LayoutError> {
+        CustomLimits::unbounded().check_size(size).map_err(LayoutError::Size)?;
+        if mines.is_empty() {
+            return Err(LayoutError::NoMines);
+        }
+        if let Some(&index) = mines.iter().find(|&&i| i >= size.capacity()) {
+            return Err(LayoutError::OutOfBounds(size.position_of(index)));
+        }
+
+        mines.sort_unstable();
+        if let Some(pair) = mines.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(LayoutError::Duplicate(size.position_of(pair[0])));
+        }
+        if mines.len() >= size.capacity() {
+            return Err(LayoutError::TooManyMines(mines.len()));
+        }
+
+        Ok(Self { size, mines })
+    }
+
+    /// Layout of the board's mines, none until they are placed.
+    pub fn of(board: &Board) -> Option<Self> {
+        if !board.is_placed() {
+            return None;
+        }
+
+        let mines = board.cells()
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| cell.is_mine())
+            .map(|(index, _)| index)
+            .collect();
+        Some(Self { size: board.size(), mines })
+    }
+
+    #[inline(always)]
+    pub fn size(&self) -> GridSize { self.size }
+
+    #[inline(always)]
+    pub fn mines(&self) -> &[usize] { &self.mines }
+}
+
+#[derive(Debug)]
+pub(crate) enum LayoutError {
+    // not even the header is there
+    Truncated,
+    // size outside of what a custom difficulty allows
+    Size(DifficultyError),
+    NoMines,
+    // the header's mine count doesn't match the mines that follow
+    MineCount {
+        header: usize,
+        found: usize,
+    },
+    OutOfBounds((usize, usize)),
+    Duplicate((usize, usize)),
+    // no cell is left without a mine
+    TooManyMines(usize),
+    // size doesn't fit in the format
+    TooLarge((usize, usize)),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::Truncated => write!(f, "file is too short for a board"),
+            LayoutError::Size(err) => write!(f, "{}", err),
+            LayoutError::NoMines => write!(f, "board has no mines"),
+            LayoutError::MineCount { header, found } => {
+                write!(f, "header says {} mines, found {}", header, found)
+            }
+            LayoutError::OutOfBounds((col, row)) => write!(f, "mine at {},{} is outside of the board", col, row),
+            LayoutError::Duplicate((col, row)) => write!(f, "mine at {},{} is there twice", col, row),
+            LayoutError::TooManyMines(mines) => write!(f, "{} mines leave no cell to reveal", mines),
+            LayoutError::TooLarge((columns, rows)) => write!(f, "a {}x{} board doesn't fit in the format", columns, rows),
+        }
+    }
+}
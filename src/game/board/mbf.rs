@@ -0,0 +1,55 @@
+use crate::game::grid::GridSize;
+
+use super::*;
+
+// columns, rows and a big endian mine count
+const HEADER: usize = 4;
+
+/// Read a layout from the MBF board format: a header with the size and the
+/// amount of mines, followed by a column and row byte per mine. Rows count
+/// from the top, the board's rows from the bottom.
+pub(crate) fn read_mbf(bytes: &[u8]) -> Result<Layout, LayoutError> {
+    if bytes.len() < HEADER {
+        return Err(LayoutError::Truncated);
+    }
+
+    let size = GridSize::new(bytes[0] as usize, bytes[1] as usize);
+    let count = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+    let found = (bytes.len() - HEADER) / 2;
+    if found != count || !(bytes.len() - HEADER).is_multiple_of(2) {
+        return Err(LayoutError::MineCount { header: count, found });
+    }
+
+    let mut mines = Vec::with_capacity(count);
+    for pair in bytes[HEADER..].chunks_exact(2) {
+        let (col, row) = (pair[0] as usize, pair[1] as usize);
+        if col >= size.columns() || row >= size.rows() {
+            return Err(LayoutError::OutOfBounds((col, row)));
+        }
+        mines.push(size.index_of(col, size.rows() - 1 - row));
+    }
+
+    Layout::new(size, mines)
+}
+
+/// Write a layout in the MBF board format, as long as its columns and rows
+/// fit in a byte.
+pub(crate) fn write_mbf(layout: &Layout) -> Result<Vec<u8>, LayoutError> {
+    let size = layout.size();
+    if size.columns() > u8::MAX as usize || size.rows() > u8::MAX as usize {
+        return Err(LayoutError::TooLarge((size.columns(), size.rows())));
+    }
+    // fewer mines than cells, so they fit in two bytes
+    let count = layout.mines().len() as u16;
+
+    let mut bytes = Vec::with_capacity(HEADER + layout.mines().len() * 2);
+    bytes.push(size.columns() as u8);
+    bytes.push(size.rows() as u8);
+    bytes.extend_from_slice(&count.to_be_bytes());
+    for &index in layout.mines() {
+        let (col, row) = size.position_of(index);
+        bytes.push(col as u8);
+        bytes.push((size.rows() - 1 - row) as u8);
+    }
+    Ok(bytes)
+}
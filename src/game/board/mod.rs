@@ -0,0 +1,21 @@
+pub(crate) use board::*;
+pub(crate) use cell::*;
+pub(crate) use first_click::*;
+pub(crate) use generator::*;
+pub(crate) use layout::*;
+pub(crate) use mbf::*;
+pub(crate) use notation::*;
+pub(crate) use probability::*;
+pub(crate) use random::*;
+pub(crate) use solver::*;
+
+mod board;
+mod cell;
+mod first_click;
+mod generator;
+mod layout;
+mod mbf;
+mod notation;
+mod probability;
+mod random;
+mod solver;
@@ -0,0 +1,320 @@
+use std::fmt;
+
+use crate::game::grid::GridSize;
+use crate::utils;
+
+use super::*;
+
+const HIDDEN: char = '.';
+const MINE: char = '*';
+const FLAG: char = 'F';
+// a flag on a cell without a mine
+const WRONG_FLAG: char = 'f';
+
+// first byte of a share code
+const CODE_VERSION: u8 = 1;
+
+/// Write a board as text: a header with the columns, rows and mines like
+/// `8x8:10`, then a line per row from the top with a character per cell.
+///
+/// `.` is a hidden cell, `*` a hidden mine, `F` a flagged mine, `f` a flag
+/// without a mine, and `0` to `8` a revealed number. Question marks are
+/// kept as hidden cells. The mines have to be placed.
+pub(crate) fn write_notation(board: &Board) -> String {
+    let size = board.size();
+    let mut text = format!("{}x{}:{}\n", size.columns(), size.rows(), board.mines());
+    for row in (0..size.rows()).rev() {
+        for col in 0..size.columns() {
+            let cell = board.cell_at(size.index_of(col, row));
+            text.push(match cell {
+                cell if cell.is_flagged() && cell.is_mine() => FLAG,
+                cell if cell.is_flagged() => WRONG_FLAG,
+                cell if cell.is_mine() => MINE,
+                cell if cell.is_revealed() => char::from(b'0' + cell.adjacent()),
+                _ => HIDDEN,
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Read a board written by `write_notation`. Blank lines and whitespace
+/// around lines don't matter.
+pub(crate) fn read_notation(text: &str) -> Result<Board, NotationError> {
+    let mut lines = text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty());
+
+    let header = lines.next().ok_or(NotationError::Header(String::new()))?;
+    let (size, count) = parse_header(header).ok_or_else(|| NotationError::Header(header.to_string()))?;
+
+    let mut mines = Vec::new();
+    let mut flagged = Vec::new();
+    let mut revealed = Vec::new();
+    let mut numbers = Vec::new();
+    let rows = lines.collect::<Vec<_>>();
+    if rows.len() != size.rows() {
+        return Err(NotationError::Rows { expected: size.rows(), found: rows.len() });
+    }
+
+    for (line, text) in rows.iter().enumerate() {
+        let found = text.chars().count();
+        if found != size.columns() {
+            return Err(NotationError::Columns { line: line + 1, expected: size.columns(), found });
+        }
+
+        let row = size.rows() - 1 - line;
+        for (col, ch) in text.chars().enumerate() {
+            let index = size.index_of(col, row);
+            match ch {
+                HIDDEN => {}
+                MINE => mines.push(index),
+                FLAG => {
+                    mines.push(index);
+                    flagged.push(index);
+                }
+                WRONG_FLAG => flagged.push(index),
+                '0'..='8' => {
+                    revealed.push(index);
+                    numbers.push((index, ch as u8 - b'0'));
+                }
+                _ => {
+                    return Err(NotationError::Char { line: line + 1, column: col + 1, ch });
+                }
+            }
+        }
+    }
+
+    if mines.len() != count {
+        return Err(NotationError::Layout(LayoutError::MineCount { header: count, found: mines.len() }));
+    }
+    let board = position(size, mines, &flagged, &revealed)?;
+
+    // numbers that don't fit the mines are most likely a typo
+    for (index, shown) in numbers {
+        let adjacent = board.cell_at(index).adjacent();
+        if adjacent != shown {
+            let (col, row) = size.position_of(index);
+            return Err(NotationError::Number {
+                line: size.rows() - row,
+                column: col + 1,
+                shown,
+                mines: adjacent,
+            });
+        }
+    }
+    Ok(board)
+}
+
+// COLSxROWS:MINES
+fn parse_header(header: &str) -> Option<(GridSize, usize)> {
+    let (size, mines) = header.split_once(':')?;
+    let (columns, rows) = size.split_once('x')?;
+    let size = GridSize::new(columns.trim().parse().ok()?, rows.trim().parse().ok()?);
+    Some((size, mines.trim().parse().ok()?))
+}
+
+/// A short code of the board that fits in a chat message: base64 of the
+/// version, the columns and rows and a bit per cell for the mines, the
+/// revealed and the flagged cells. The mines have to be placed.
+pub(crate) fn share_code(board: &Board) -> String {
+    let size = board.size();
+    let mut bytes = vec![CODE_VERSION];
+    bytes.extend_from_slice(&(size.columns() as u16).to_be_bytes());
+    bytes.extend_from_slice(&(size.rows() as u16).to_be_bytes());
+    for check in [Cell::is_mine, Cell::is_revealed, Cell::is_flagged] {
+        let mut bits = vec![0u8; size.capacity().div_ceil(8)];
+        for (index, cell) in board.cells().iter().enumerate() {
+            if check(cell) {
+                bits[index / 8] |= 1 << (index % 8);
+            }
+        }
+        bytes.extend(bits);
+    }
+    utils::base64_encode(&bytes)
+}
+
+/// Read a board from a code written by `share_code`.
+pub(crate) fn read_share_code(code: &str) -> Result<Board, NotationError> {
+    let bytes = utils::base64_decode(code.trim()).ok_or(NotationError::Code("not a share code"))?;
+    if bytes.len() < 5 {
+        return Err(NotationError::Code("too short"));
+    }
+    if bytes[0] != CODE_VERSION {
+        return Err(NotationError::Code("made by a newer version of the game"));
+    }
+
+    let size = GridSize::new(
+        u16::from_be_bytes([bytes[1], bytes[2]]) as usize,
+        u16::from_be_bytes([bytes[3], bytes[4]]) as usize,
+    );
+    let len = size.capacity().div_ceil(8);
+    if bytes.len() != 5 + len * 3 {
+        return Err(NotationError::Code("length doesn't fit the board's size"));
+    }
+
+    let cells = |part: usize| -> Vec<usize> {
+        let bits = &bytes[5 + part * len..5 + (part + 1) * len];
+        (0..size.capacity())
+            .filter(|&index| bits[index / 8] & (1 << (index % 8)) != 0)
+            .collect()
+    };
+    position(size, cells(0), &cells(2), &cells(1))
+}
+
+// board with the mines, flags and revealed cells
+fn position(size: GridSize, mines: Vec<usize>, flagged: &[usize], revealed: &[usize]) -> Result<Board, NotationError> {
+    let layout = Layout::new(size, mines).map_err(NotationError::Layout)?;
+    let mut board = Board::with_layout(&layout);
+    for &index in flagged {
+        board.cycle_mark(index, false);
+    }
+    for &index in revealed {
+        if board.cell_at(index).is_mine() || board.cell_at(index).is_flagged() {
+            return Err(NotationError::Revealed(size.position_of(index)));
+        }
+        board.reveal(index);
+    }
+    Ok(board)
+}
+
+#[derive(Debug)]
+pub(crate) enum NotationError {
+    // first line isn't COLSxROWS:MINES
+    Header(String),
+    Rows {
+        expected: usize,
+        found: usize,
+    },
+    Columns {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    Char {
+        line: usize,
+        column: usize,
+        ch: char,
+    },
+    // revealed number that doesn't match the mines around it
+    Number {
+        line: usize,
+        column: usize,
+        shown: u8,
+        mines: u8,
+    },
+    // a revealed cell is a mine or flagged
+    Revealed((usize, usize)),
+    Layout(LayoutError),
+    Code(&'static str),
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotationError::Header(header) => write!(f, "header '{}' isn't COLSxROWS:MINES", header),
+            NotationError::Rows { expected, found } => write!(f, "expected {} rows, found {}", expected, found),
+            NotationError::Columns { line, expected, found } => {
+                write!(f, "row {} has {} cells instead of {}", line, found, expected)
+            }
+            NotationError::Char { line, column, ch } => {
+                write!(f, "row {} column {}: '{}' isn't one of . * F f 0-8", line, column, ch)
+            }
+            NotationError::Number { line, column, shown, mines } => {
+                write!(f, "row {} column {} shows {} but has {} mines around it", line, column, shown, mines)
+            }
+            NotationError::Revealed((col, row)) => write!(f, "revealed cell at {},{} is a mine or flagged", col, row),
+            NotationError::Layout(err) => write!(f, "{}", err),
+            NotationError::Code(reason) => write!(f, "invalid share code: {}", reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 8x8 board with mines in the corners of the bottom row, a flag on one
+    // of them and a wrong one, and the top left opened
+    fn position() -> Board {
+        let size = GridSize::new(8, 8);
+        let mut board = Board::new(size, 2);
+        board.place_mines(&[size.index_of(0, 0), size.index_of(7, 0)]);
+        board.cycle_mark(size.index_of(0, 0), false);
+        board.cycle_mark(size.index_of(5, 1), false);
+        board.reveal(size.index_of(0, 7));
+        board
+    }
+
+    fn same_cells(a: &Board, b: &Board) {
+        assert_eq!((a.size().columns(), a.size().rows()), (b.size().columns(), b.size().rows()));
+        assert_eq!(a.mines(), b.mines());
+        for (index, (a, b)) in a.cells().iter().zip(b.cells()).enumerate() {
+            assert_eq!(a.is_mine(), b.is_mine(), "mine at {}", index);
+            assert_eq!(a.is_revealed(), b.is_revealed(), "revealed at {}", index);
+            assert_eq!(a.is_flagged(), b.is_flagged(), "flag at {}", index);
+        }
+    }
+
+    #[test]
+    fn notation_round_trip() {
+        let board = position();
+        let text = write_notation(&board);
+        assert_eq!(text, format!("8x8:2\n{}11000f11\nF100001*\n", "00000000\n".repeat(6)));
+
+        let read = read_notation(&text).unwrap();
+        same_cells(&board, &read);
+        assert_eq!(write_notation(&read), text);
+    }
+
+    #[test]
+    fn notation_ignores_blank_lines_and_whitespace() {
+        let text = write_notation(&position());
+        let spaced = text.lines().map(|line| format!("  {}  \n\n", line)).collect::<String>();
+        same_cells(&position(), &read_notation(&spaced).unwrap());
+    }
+
+    #[test]
+    fn share_code_round_trip() {
+        let board = position();
+        let code = share_code(&board);
+        assert!(code.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_'));
+        same_cells(&board, &read_share_code(&code).unwrap());
+    }
+
+    #[test]
+    fn notation_rejects_bad_input() {
+        let text = write_notation(&position());
+        let lines = text.lines().collect::<Vec<_>>();
+        let with = |line: usize, row: &str| {
+            let mut lines = lines.clone();
+            lines[line] = row;
+            lines.join("\n")
+        };
+
+        assert!(matches!(read_notation(""), Err(NotationError::Header(_))));
+        assert!(matches!(read_notation("8by8:2"), Err(NotationError::Header(_))));
+        assert!(matches!(read_notation(&with(0, "8x9:2")), Err(NotationError::Rows { expected: 9, found: 8 })));
+        assert!(matches!(read_notation(&with(2, ".......")), Err(NotationError::Columns { line: 2, .. })));
+        assert!(matches!(read_notation(&with(2, "..x.....")), Err(NotationError::Char { line: 2, column: 3, ch: 'x' })));
+        assert!(matches!(read_notation(&with(1, "00000001")), Err(NotationError::Number { line: 1, column: 8, .. })));
+        assert!(matches!(read_notation(&with(0, "8x8:3")), Err(NotationError::Layout(LayoutError::MineCount { .. }))));
+
+        let empty = format!("8x8:0\n{}", "........\n".repeat(8));
+        assert!(matches!(read_notation(&empty), Err(NotationError::Layout(LayoutError::NoMines))));
+        assert!(matches!(read_notation("2x1:1\n*0"), Err(NotationError::Layout(LayoutError::Size(_)))));
+    }
+
+    #[test]
+    fn share_code_rejects_bad_input() {
+        let code = share_code(&position());
+        assert!(matches!(read_share_code("not a code!"), Err(NotationError::Code(_))));
+        assert!(matches!(read_share_code("AQAI"), Err(NotationError::Code("too short"))));
+        assert!(matches!(read_share_code(&code[..code.len() - 2]), Err(NotationError::Code(_))));
+
+        let mut bytes = utils::base64_decode(&code).unwrap();
+        bytes[0] = CODE_VERSION + 1;
+        assert!(matches!(read_share_code(&utils::base64_encode(&bytes)), Err(NotationError::Code(_))));
+    }
+}
@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+
+use crate::game::grid::GridSize;
+
+use super::*;
+
+// the revealed numbers around a part of the frontier, the cells of each in
+// order; the same key always has the same odds
+type ComponentKey = Vec<(Vec<usize>, usize)>;
+
+/// How the configurations of mines of an independent part of the frontier
+/// spread over their amounts of mines. Weights are scaled so the largest is
+/// one, only their ratios matter.
+struct ComponentOdds {
+    cells: Vec<usize>,
+    // configurations by amount of mines
+    weights: Vec<f64>,
+    // of those, the ones with a mine in each of the cells
+    mine_weights: Vec<Vec<f64>>,
+}
+
+// cells that are next to exactly the same numbers, they are interchangeable
+// so only the amount of mines among them matters
+struct Group {
+    cells: Vec<usize>,
+    constraints: Vec<usize>,
+}
+
+/// What the player sees of a board: the revealed numbers, by index.
+pub(crate) fn visible_numbers(board: &Board) -> Vec<Option<u8>> {
+    board.cells()
+        .iter()
+        .map(|cell| Some(cell.adjacent()).filter(|_| cell.is_revealed() && !cell.is_mine()))
+        .collect()
+}
+
+/// Exact chance of each covered cell to be a mine, given the revealed
+/// numbers and the amount of mines. Flags are ignored, they might be wrong.
+///
+/// Covered cells next to a number make up the frontier, which is split into
+/// parts that don't share a number. The configurations of mines of each
+/// part are enumerated, then weighted by the ways the remaining mines fit
+/// in the covered cells away from the frontier. Parts that didn't change
+/// since the last time are kept, so a reveal only costs the parts it
+/// touches.
+#[derive(Default)]
+pub(crate) struct MineProbabilities {
+    cache: HashMap<ComponentKey, ComponentOdds>,
+    // the odds depend on the amount of mines too
+    mines: usize,
+}
+
+impl MineProbabilities {
+    /// Chance by index, `None` for the revealed cells. There is no answer
+    /// when the numbers can't all be right.
+    pub fn compute(&mut self, size: GridSize, mines: usize, numbers: &[Option<u8>]) -> Option<Vec<Option<f32>>> {
+        if mines != self.mines {
+            self.cache.clear();
+            self.mines = mines;
+        }
+
+        let keys = components_of(size, numbers);
+        // parts that are gone won't come back
+        let mut cache = HashMap::with_capacity(keys.len());
+        for key in keys.iter() {
+            let odds = self.cache.remove(key).unwrap_or_else(|| enumerate(key, mines));
+            cache.insert(key.clone(), odds);
+        }
+        self.cache = cache;
+        let components = keys.iter().map(|key| &self.cache[key]).collect::<Vec<_>>();
+
+        let covered = numbers.iter().filter(|number| number.is_none()).count();
+        let frontier = components.iter().map(|odds| odds.cells.len()).sum::<usize>();
+        let others = covered - frontier;
+
+        // ways to place the rest of the mines away from the frontier, by the
+        // amount of mines on the frontier
+        let ln_factorials = ln_factorials(others);
+        let ln_rest = (0..=mines)
+            .map(|on_frontier| {
+                let rest = mines - on_frontier;
+                if rest > others {
+                    return None;
+                }
+                Some(ln_factorials[others] - ln_factorials[rest] - ln_factorials[others - rest])
+            })
+            .collect::<Vec<_>>();
+        let max = ln_rest.iter().flatten().copied().fold(f64::NEG_INFINITY, f64::max);
+        let rest = ln_rest.iter()
+            .map(|ln| ln.map_or(0., |ln| (ln - max).exp()))
+            .collect::<Vec<_>>();
+
+        // configurations of every part but one, by their amount of mines
+        let mut prefix = vec![vec![1.]];
+        for odds in components.iter() {
+            prefix.push(convolve(prefix.last().unwrap(), &odds.weights, mines));
+        }
+        let mut suffix = vec![vec![1.]];
+        for odds in components.iter().rev() {
+            suffix.push(convolve(suffix.last().unwrap(), &odds.weights, mines));
+        }
+        suffix.reverse();
+
+        let all = prefix.last().unwrap();
+        let total = all.iter().zip(&rest).map(|(weight, rest)| weight * rest).sum::<f64>();
+        if total <= 0. || !total.is_finite() {
+            return None;
+        }
+
+        let mut chances = numbers.iter()
+            .map(|number| if number.is_none() { Some(0.) } else { None })
+            .collect::<Vec<_>>();
+
+        if others > 0 {
+            let expected = all.iter()
+                .zip(&rest)
+                .enumerate()
+                .map(|(on_frontier, (weight, rest))| weight * rest * (mines - on_frontier) as f64)
+                .sum::<f64>();
+            let chance = (expected / total / others as f64) as f32;
+            for value in chances.iter_mut().flatten() {
+                *value = chance;
+            }
+        }
+
+        for (c, odds) in components.iter().enumerate() {
+            let without = convolve(&prefix[c], &suffix[c + 1], mines);
+            // weight of the rest of the board, by the amount of mines of this part
+            let outside = (0..odds.weights.len())
+                .map(|own| {
+                    without.iter()
+                        .enumerate()
+                        .filter(|(other, _)| own + other <= mines)
+                        .map(|(other, weight)| weight * rest[own + other])
+                        .sum::<f64>()
+                })
+                .collect::<Vec<_>>();
+
+            for (i, &index) in odds.cells.iter().enumerate() {
+                let weight = odds.mine_weights
+                    .iter()
+                    .zip(&outside)
+                    .map(|(mine_weights, outside)| mine_weights[i] * outside)
+                    .sum::<f64>();
+                chances[index] = Some((weight / total) as f32);
+            }
+        }
+        Some(chances)
+    }
+}
+
+// the numbers next to covered cells, split in parts that share no cells
+fn components_of(size: GridSize, numbers: &[Option<u8>]) -> Vec<ComponentKey> {
+    let mut constraints = Vec::new();
+    for (index, number) in numbers.iter().enumerate() {
+        let number = match number {
+            Some(number) => *number as usize,
+            None => { continue; }
+        };
+        let mut cells = size.neighbours(index)
+            .filter(|&i| numbers[i].is_none())
+            .collect::<Vec<_>>();
+        if !cells.is_empty() {
+            cells.sort_unstable();
+            constraints.push((cells, number));
+        }
+    }
+
+    // join the constraints that share a cell
+    let mut parent = (0..constraints.len()).collect::<Vec<_>>();
+    fn root(parent: &mut [usize], mut c: usize) -> usize {
+        while parent[c] != c {
+            parent[c] = parent[parent[c]];
+            c = parent[c];
+        }
+        c
+    }
+    let mut owner = HashMap::new();
+    for (c, (cells, _)) in constraints.iter().enumerate() {
+        for &cell in cells.iter() {
+            let other = *owner.entry(cell).or_insert(c);
+            let (a, b) = (root(&mut parent, c), root(&mut parent, other));
+            parent[a] = b;
+        }
+    }
+
+    let mut components = HashMap::<usize, ComponentKey>::new();
+    for (c, constraint) in constraints.into_iter().enumerate() {
+        components.entry(root(&mut parent, c)).or_default().push(constraint);
+    }
+    components.into_values()
+        .map(|mut key| {
+            key.sort_unstable();
+            key.dedup();
+            key
+        })
+        .collect()
+}
+
+// weigh every configuration of mines of a part of the frontier
+fn enumerate(key: &ComponentKey, max_mines: usize) -> ComponentOdds {
+    let mut cells = key.iter().flat_map(|(cells, _)| cells.iter().copied()).collect::<Vec<_>>();
+    cells.sort_unstable();
+    cells.dedup();
+
+    let mut by_constraints = HashMap::<Vec<usize>, Vec<usize>>::new();
+    for (i, cell) in cells.iter().enumerate() {
+        let constraints = key.iter()
+            .enumerate()
+            .filter(|(_, (cells, _))| cells.binary_search(cell).is_ok())
+            .map(|(c, _)| c)
+            .collect();
+        by_constraints.entry(constraints).or_default().push(i);
+    }
+    let groups = order_groups(by_constraints
+        .into_iter()
+        .map(|(constraints, cells)| Group { cells, constraints })
+        .collect());
+
+    let len = cells.len().min(max_mines) + 1;
+    let mut search = Search {
+        groups: &groups,
+        needed: key.iter().map(|(_, mines)| *mines).collect(),
+        open: key.iter().map(|(cells, _)| cells.len()).collect(),
+        mines: vec![0; groups.len()],
+        max_mines,
+        weights: vec![0.; len],
+        group_weights: vec![vec![0.; groups.len()]; len],
+    };
+    search.run(0, 0, 1.);
+
+    let scale = search.weights.iter().copied().fold(0., f64::max);
+    let scale = if scale > 0. { scale } else { 1. };
+    let mut mine_weights = vec![vec![0.; cells.len()]; len];
+    for (mines, group_weights) in search.group_weights.iter().enumerate() {
+        for (group, weight) in groups.iter().zip(group_weights) {
+            // any cell of a group is as likely as the others
+            let per_cell = weight / group.cells.len() as f64 / scale;
+            for &i in group.cells.iter() {
+                mine_weights[mines][i] = per_cell;
+            }
+        }
+    }
+    ComponentOdds {
+        cells,
+        weights: search.weights.iter().map(|weight| weight / scale).collect(),
+        mine_weights,
+    }
+}
+
+// groups in the order they're reached from the first one through shared
+// numbers, so numbers are settled early and bad branches end soon
+fn order_groups(mut groups: Vec<Group>) -> Vec<Group> {
+    let mut ordered = Vec::with_capacity(groups.len());
+    while !groups.is_empty() {
+        let next = if ordered.is_empty() {
+            0
+        } else {
+            let shares = |group: &Group| {
+                ordered.iter().any(|placed: &Group| placed.constraints.iter().any(|c| group.constraints.contains(c)))
+            };
+            groups.iter().position(shares).unwrap_or(0)
+        };
+        ordered.push(groups.swap_remove(next));
+    }
+    ordered
+}
+
+struct Search<'a> {
+    groups: &'a [Group],
+    // mines each number still needs
+    needed: Vec<usize>,
+    // cells of each number that aren't decided yet
+    open: Vec<usize>,
+    mines: Vec<usize>,
+    max_mines: usize,
+    weights: Vec<f64>,
+    // mines of each group, weighted, by the configuration's amount of mines
+    group_weights: Vec<Vec<f64>>,
+}
+
+impl<'a> Search<'a> {
+    fn run(&mut self, g: usize, total: usize, weight: f64) {
+        if g == self.groups.len() {
+            self.weights[total] += weight;
+            for (group_weight, &mines) in self.group_weights[total].iter_mut().zip(&self.mines) {
+                *group_weight += weight * mines as f64;
+            }
+            return;
+        }
+
+        let group = &self.groups[g];
+        let size = group.cells.len();
+        let mut min = 0;
+        let mut max = size.min(self.max_mines - total);
+        for &c in group.constraints.iter() {
+            let open = self.open[c] - size;
+            min = min.max(self.needed[c].saturating_sub(open));
+            max = max.min(self.needed[c]);
+        }
+        if min > max {
+            return;
+        }
+
+        for &c in group.constraints.iter() {
+            self.open[c] -= size;
+        }
+        for mines in min..=max {
+            for &c in group.constraints.iter() {
+                self.needed[c] -= mines;
+            }
+            self.mines[g] = mines;
+            self.run(g + 1, total + mines, weight * choose(size, mines));
+            for &c in group.constraints.iter() {
+                self.needed[c] += mines;
+            }
+        }
+        for &c in group.constraints.iter() {
+            self.open[c] += size;
+        }
+    }
+}
+
+// ways to pick k of n, small numbers only
+#[inline]
+fn choose(n: usize, k: usize) -> f64 {
+    (0..k).fold(1., |ways, i| ways * (n - i) as f64 / (i + 1) as f64)
+}
+
+#[inline]
+fn ln_factorials(n: usize) -> Vec<f64> {
+    let mut ln = vec![0.; n + 1];
+    for i in 1..=n {
+        ln[i] = ln[i - 1] + (i as f64).ln();
+    }
+    ln
+}
+
+// weights of two parts together by their amount of mines, up to max
+fn convolve(a: &[f64], b: &[f64], max: usize) -> Vec<f64> {
+    let mut sum = vec![0.; (a.len() + b.len() - 1).min(max + 1)];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            if i + j < sum.len() {
+                sum[i + j] += x * y;
+            }
+        }
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_chances(chances: &[Option<f32>], expected: &[Option<f32>]) {
+        assert_eq!(chances.len(), expected.len());
+        for (index, (chance, expected)) in chances.iter().zip(expected).enumerate() {
+            match (chance, expected) {
+                (Some(chance), Some(expected)) => assert!((chance - expected).abs() < 1e-5, "cell {}: {} != {}", index, chance, expected),
+                _ => assert_eq!(chance, expected, "cell {}", index),
+            }
+        }
+    }
+
+    #[test]
+    fn one_two_one() {
+        // 1 2 1
+        // ? ? ?
+        let size = GridSize::new(3, 2);
+        let numbers = [Some(1), Some(2), Some(1), None, None, None];
+        let chances = MineProbabilities::default().compute(size, 2, &numbers).unwrap();
+        assert_chances(&chances, &[None, None, None, Some(1.), Some(0.), Some(1.)]);
+    }
+
+    #[test]
+    fn fifty_fifty() {
+        // ? 1 ?
+        let size = GridSize::new(3, 1);
+        let numbers = [None, Some(1), None];
+        let chances = MineProbabilities::default().compute(size, 1, &numbers).unwrap();
+        assert_chances(&chances, &[Some(0.5), None, Some(0.5)]);
+    }
+
+    #[test]
+    fn off_frontier() {
+        // ? 1 ? . . .
+        // the 1 takes one of the 2 mines, the other is anywhere of the 3
+        // cells away from it
+        let size = GridSize::new(6, 1);
+        let numbers = [None, Some(1), None, None, None, None];
+        let chances = MineProbabilities::default().compute(size, 2, &numbers).unwrap();
+        let other = (2. - 1.) / 3.;
+        assert_chances(&chances, &[Some(0.5), None, Some(0.5), Some(other), Some(other), Some(other)]);
+    }
+
+    #[test]
+    fn cache_keeps_odds() {
+        let size = GridSize::new(7, 1);
+        let mut probabilities = MineProbabilities::default();
+        let before = [None, Some(1), None, None, None, None, None];
+        probabilities.compute(size, 2, &before).unwrap();
+
+        // a reveal away from the first part
+        let after = [None, Some(1), None, None, None, Some(1), None];
+        let cached = probabilities.compute(size, 2, &after).unwrap();
+        let fresh = MineProbabilities::default().compute(size, 2, &after).unwrap();
+        assert_chances(&cached, &fresh);
+        assert_chances(&cached, &[Some(0.5), None, Some(0.5), Some(0.), Some(0.5), None, Some(0.5)]);
+    }
+
+    #[test]
+    fn inconsistent_numbers() {
+        // a 3 with two covered neighbours
+        let size = GridSize::new(3, 1);
+        let numbers = [None, Some(3), None];
+        assert!(MineProbabilities::default().compute(size, 3, &numbers).is_none());
+
+        // more mines around than on the board
+        let numbers = [None, Some(2), None];
+        assert!(MineProbabilities::default().compute(size, 1, &numbers).is_none());
+    }
+}
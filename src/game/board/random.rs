@@ -0,0 +1,58 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Random number generator to place mines with. ChaCha8 generates the same
+/// numbers for the same seed, on every platform.
+pub(crate) type BoardRng = ChaCha8Rng;
+
+#[inline]
+pub(crate) fn seeded_rng(seed: u64) -> BoardRng {
+    BoardRng::seed_from_u64(seed)
+}
+
+/// Pick an amount of random items from candidates, using a partial
+/// Fisher-Yates shuffle. Ranges are u64 so the picked items don't depend on
+/// the platform's usize. The amount has to fit in the candidates, the mines
+/// of a difficulty are checked against the first click guarantee for that.
+pub(crate) fn pick<R: Rng + ?Sized>(rng: &mut R, mut candidates: Vec<usize>, amount: usize) -> Vec<usize> {
+    assert!(amount <= candidates.len(), "{} mines don't fit in {} cells", amount, candidates.len());
+    for i in 0..amount {
+        let j = rng.gen_range(i as u64..candidates.len() as u64) as usize;
+        candidates.swap(i, j);
+    }
+
+    candidates.truncate(amount);
+    candidates
+}
+
+/// Pick an amount of random items from candidates, one at a time. The chance
+/// of a candidate being picked is its weight relative to the others, weights
+/// are determined again after each pick. The weight function gets to know
+/// which items are picked so far.
+pub(crate) fn pick_weighted<R, W>(rng: &mut R, mut candidates: Vec<usize>, amount: usize, mut weight: W) -> Vec<usize>
+    where R: Rng + ?Sized,
+          W: FnMut(usize, &[bool]) -> u64,
+{
+    assert!(amount <= candidates.len(), "{} mines don't fit in {} cells", amount, candidates.len());
+    let mut picked = Vec::with_capacity(amount);
+    let mut is_picked = vec![false; candidates.iter().max().map_or(0, |max| max + 1)];
+    let mut weights = Vec::with_capacity(candidates.len());
+
+    while picked.len() < amount {
+        weights.clear();
+        weights.extend(candidates.iter().map(|&i| weight(i, &is_picked).max(1)));
+
+        let total = weights.iter().sum::<u64>();
+        let mut target = rng.gen_range(0..total);
+        let mut pos = 0;
+        while target >= weights[pos] {
+            target -= weights[pos];
+            pos += 1;
+        }
+
+        let index = candidates.swap_remove(pos);
+        is_picked[index] = true;
+        picked.push(index);
+    }
+    picked
+}
@@ -0,0 +1,404 @@
+use crate::game::grid::GridSize;
+
+use super::*;
+
+/// What can be known about a cell, given the revealed numbers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Knowledge {
+    Unknown,
+    Safe,
+    Mine,
+}
+
+/// Cells that are proven to be safe or a mine.
+#[derive(Debug, Default)]
+pub(crate) struct Deduction {
+    pub safe: Vec<usize>,
+    pub mines: Vec<usize>,
+}
+
+impl Deduction {
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool { self.safe.is_empty() && self.mines.is_empty() }
+
+    #[inline]
+    fn add(&mut self, index: usize, knowledge: Knowledge) {
+        match knowledge {
+            Knowledge::Safe if !self.safe.contains(&index) => { self.safe.push(index); }
+            Knowledge::Mine if !self.mines.contains(&index) => { self.mines.push(index); }
+            _ => {}
+        }
+    }
+}
+
+// the amount of mines within a set of unknown cells
+struct Constraint {
+    cells: Vec<usize>,
+    mines: usize,
+}
+
+/// Deduces safe cells and mines from the revealed numbers only, the way a
+/// player would without guessing.
+pub(crate) struct Solver {
+    size: GridSize,
+    mines: usize,
+    numbers: Vec<Option<u8>>,
+    known: Vec<Knowledge>,
+    revealed: usize,
+    known_mines: usize,
+}
+
+impl Solver {
+    pub fn new(size: GridSize, mines: usize) -> Self {
+        Self {
+            size,
+            mines,
+            numbers: vec![None; size.capacity()],
+            known: vec![Knowledge::Unknown; size.capacity()],
+            revealed: 0,
+            known_mines: 0,
+        }
+    }
+
+    /// Solver that knows what the player sees on the board. Flags are
+    /// ignored, they might be wrong.
+    pub fn from_board(board: &Board) -> Self {
+        let mut solver = Self::new(board.size(), board.mines());
+        for (index, cell) in board.cells().iter().enumerate() {
+            if cell.is_revealed() && !cell.is_mine() {
+                solver.reveal(index, cell.adjacent());
+            }
+        }
+        solver
+    }
+
+    /// Whether every cell without a mine is revealed.
+    #[inline]
+    pub fn is_solved(&self) -> bool {
+        self.revealed + self.mines >= self.size.capacity()
+    }
+
+    #[inline]
+    pub fn reveal(&mut self, index: usize, number: u8) {
+        if self.numbers[index].is_none() {
+            self.revealed += 1;
+        }
+        self.numbers[index] = Some(number);
+        self.known[index] = Knowledge::Safe;
+    }
+
+    #[inline]
+    pub fn mark_mine(&mut self, index: usize) {
+        if self.known[index] != Knowledge::Mine {
+            self.known[index] = Knowledge::Mine;
+            self.known_mines += 1;
+        }
+    }
+
+    /// Reveal the cell at index using the board's layout, opening up empty
+    /// regions like a player's click would.
+    pub fn open(&mut self, board: &Board, index: usize) {
+        let mut stack = vec![index];
+        while let Some(index) = stack.pop() {
+            if self.numbers[index].is_some() {
+                continue;
+            }
+
+            let adjacent = board.cell_at(index).adjacent();
+            self.reveal(index, adjacent);
+            if adjacent == 0 {
+                stack.extend(self.size
+                    .neighbours(index)
+                    .filter(|&i| self.numbers[i].is_none()));
+            }
+        }
+    }
+
+    /// Find cells that are certainly safe or certainly a mine. Simple rules
+    /// are tried first, the more expensive ones only when those find nothing.
+    pub fn deduce(&self) -> Deduction {
+        let constraints = self.constraints();
+
+        let mut deduction = Deduction::default();
+        for constraint in constraints.iter() {
+            Self::deduce_single(constraint, &mut deduction);
+        }
+        if !deduction.is_empty() {
+            return deduction;
+        }
+
+        self.deduce_pairs(&constraints, &mut deduction);
+        if !deduction.is_empty() {
+            return deduction;
+        }
+
+        self.deduce_global(&mut deduction);
+        deduction
+    }
+
+    // a constraint per revealed number with unknown neighbours
+    fn constraints(&self) -> Vec<Constraint> {
+        let mut constraints = Vec::new();
+        for (index, number) in self.numbers.iter().enumerate() {
+            let number = match number {
+                Some(number) => *number as usize,
+                None => { continue; }
+            };
+
+            let mut cells = Vec::new();
+            let mut mines = 0;
+            for i in self.size.neighbours(index) {
+                match self.known[i] {
+                    Knowledge::Unknown => { cells.push(i); }
+                    Knowledge::Mine => { mines += 1; }
+                    Knowledge::Safe => {}
+                }
+            }
+
+            if !cells.is_empty() {
+                cells.sort_unstable();
+                constraints.push(Constraint { cells, mines: number.saturating_sub(mines) });
+            }
+        }
+        constraints
+    }
+
+    // all cells are mines, or none of them are
+    fn deduce_single(constraint: &Constraint, deduction: &mut Deduction) {
+        let knowledge = if constraint.mines == 0 {
+            Knowledge::Safe
+        } else if constraint.mines == constraint.cells.len() {
+            Knowledge::Mine
+        } else {
+            return;
+        };
+
+        for &i in constraint.cells.iter() {
+            deduction.add(i, knowledge);
+        }
+    }
+
+    // two overlapping constraints limit the amount of mines in their overlap,
+    // which may decide the cells outside of it
+    fn deduce_pairs(&self, constraints: &[Constraint], deduction: &mut Deduction) {
+        let mut by_cell = vec![Vec::new(); self.size.capacity()];
+        for (c, constraint) in constraints.iter().enumerate() {
+            for &i in constraint.cells.iter() {
+                by_cell[i].push(c);
+            }
+        }
+
+        let mut seen = Vec::new();
+        for (a, constraint_a) in constraints.iter().enumerate() {
+            seen.clear();
+            for &i in constraint_a.cells.iter() {
+                for &b in by_cell[i].iter() {
+                    if b <= a || seen.contains(&b) {
+                        continue;
+                    }
+                    seen.push(b);
+                    Self::deduce_pair(constraint_a, &constraints[b], deduction);
+                }
+            }
+        }
+    }
+
+    fn deduce_pair(a: &Constraint, b: &Constraint, deduction: &mut Deduction) {
+        let overlap = a.cells.iter().filter(|i| b.cells.contains(i)).count();
+        let only_a = a.cells.len() - overlap;
+        let only_b = b.cells.len() - overlap;
+
+        // bounds of the amount of mines in the overlap
+        let min = a.mines.saturating_sub(only_a).max(b.mines.saturating_sub(only_b));
+        let max = overlap.min(a.mines).min(b.mines);
+        if min > max {
+            return;
+        }
+
+        for (constraint, other, only) in [(a, b, only_a), (b, a, only_b)] {
+            if only == 0 {
+                continue;
+            }
+
+            let knowledge = if constraint.mines - min == 0 {
+                Knowledge::Safe
+            } else if constraint.mines - max == only {
+                Knowledge::Mine
+            } else {
+                continue;
+            };
+
+            for &i in constraint.cells.iter() {
+                if !other.cells.contains(&i) {
+                    deduction.add(i, knowledge);
+                }
+            }
+        }
+    }
+
+    // all remaining mines are known, or all unknown cells are mines
+    fn deduce_global(&self, deduction: &mut Deduction) {
+        let unknown = self.known.iter().filter(|k| **k == Knowledge::Unknown).count();
+        if unknown == 0 {
+            return;
+        }
+
+        let remaining = self.mines.saturating_sub(self.known_mines);
+        let knowledge = if remaining == 0 {
+            Knowledge::Safe
+        } else if remaining == unknown {
+            Knowledge::Mine
+        } else {
+            return;
+        };
+
+        for (index, k) in self.known.iter().enumerate() {
+            if *k == Knowledge::Unknown {
+                deduction.add(index, knowledge);
+            }
+        }
+    }
+}
+
+/// Whether the board's layout can be solved from the cell at start, without
+/// ever having to guess.
+pub(crate) fn is_solvable(board: &Board, start: usize) -> bool {
+    if board.cell_at(start).is_mine() {
+        return false;
+    }
+
+    let mut solver = Solver::new(board.size(), board.mines());
+    solver.open(board, start);
+
+    while !solver.is_solved() {
+        let deduction = solver.deduce();
+        if deduction.is_empty() {
+            return false;
+        }
+
+        for index in deduction.mines {
+            solver.mark_mine(index);
+        }
+        for index in deduction.safe {
+            solver.open(board, index);
+        }
+    }
+    true
+}
+
+/// A cell the player can be sure about from the revealed numbers: a safe
+/// cell if there is one, or else a mine that isn't flagged yet. Flags don't
+/// count as proof, they might be wrong.
+pub(crate) fn find_hint(board: &Board) -> Option<(usize, Knowledge)> {
+    let mut solver = Solver::from_board(board);
+    loop {
+        let deduction = solver.deduce();
+        if deduction.is_empty() {
+            return None;
+        }
+
+        if let Some(&index) = deduction.safe.first() {
+            return Some((index, Knowledge::Safe));
+        }
+        if let Some(&index) = deduction.mines.iter().find(|&&index| !board.cell_at(index).is_flagged()) {
+            return Some((index, Knowledge::Mine));
+        }
+
+        // the mines found are flagged already, they may lead further
+        for index in deduction.mines {
+            solver.mark_mine(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(size: GridSize, mines: &[(usize, usize)]) -> Board {
+        let mut board = Board::new(size, mines.len());
+        let indexes = mines.iter().map(|&(col, row)| size.index_of(col, row)).collect::<Vec<_>>();
+        board.place_mines(&indexes);
+        board
+    }
+
+    #[test]
+    fn solvable_layout() {
+        // . 2 *
+        // . 2 *
+        let size = GridSize::new(3, 2);
+        let board = board(size, &[(2, 0), (2, 1)]);
+        assert!(is_solvable(&board, size.index_of(0, 0)));
+        // starting on a mine never works
+        assert!(!is_solvable(&board, size.index_of(2, 0)));
+    }
+
+    #[test]
+    fn fifty_fifty_needs_a_guess() {
+        // . 1 *
+        // . 1 .
+        let size = GridSize::new(3, 2);
+        let board = board(size, &[(2, 0)]);
+        assert!(!is_solvable(&board, size.index_of(0, 0)));
+    }
+
+    #[test]
+    fn deduce_single() {
+        // a 0 has only safe neighbours
+        let size = GridSize::new(3, 1);
+        let mut solver = Solver::new(size, 1);
+        solver.reveal(1, 0);
+        let deduction = solver.deduce();
+        assert_eq!(deduction.safe, vec![0, 2]);
+        assert!(deduction.mines.is_empty());
+
+        // a 1 with a single unknown neighbour
+        let mut solver = Solver::new(size, 1);
+        solver.reveal(0, 0);
+        solver.reveal(1, 1);
+        let deduction = solver.deduce();
+        assert_eq!(deduction.mines, vec![2]);
+        assert!(deduction.safe.is_empty());
+    }
+
+    #[test]
+    fn deduce_pair() {
+        // 1 2 1
+        // ? ? ?
+        // none of the numbers decides anything on their own, the 2 next to
+        // either 1 puts mines on both ends
+        let size = GridSize::new(3, 2);
+        let mut solver = Solver::new(size, 2);
+        solver.reveal(size.index_of(0, 0), 1);
+        solver.reveal(size.index_of(1, 0), 2);
+        solver.reveal(size.index_of(2, 0), 1);
+
+        let mut single = Deduction::default();
+        for constraint in solver.constraints().iter() {
+            Solver::deduce_single(constraint, &mut single);
+        }
+        assert!(single.is_empty());
+
+        let mut deduction = solver.deduce();
+        deduction.mines.sort_unstable();
+        assert_eq!(deduction.mines, vec![size.index_of(0, 1), size.index_of(2, 1)]);
+        assert!(deduction.safe.is_empty());
+    }
+
+    #[test]
+    fn deduce_global() {
+        // the only mine is known, the rest is safe
+        let size = GridSize::new(3, 3);
+        let mut solver = Solver::new(size, 1);
+        solver.mark_mine(0);
+        let deduction = solver.deduce();
+        assert_eq!(deduction.safe, (1..9).collect::<Vec<_>>());
+
+        // as many unknown cells as mines
+        let mut solver = Solver::new(GridSize::new(2, 1), 2);
+        assert_eq!(solver.deduce().mines, vec![0, 1]);
+        solver.mark_mine(0);
+        solver.mark_mine(1);
+        assert!(solver.deduce().is_empty());
+    }
+}
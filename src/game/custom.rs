@@ -0,0 +1,314 @@
+use std::ops::Deref;
+
+use bevy::prelude::*;
+use bevy::window::WindowId;
+use bevy::winit::WinitWindows;
+
+use super::*;
+
+// the header and edges around the grid, see update_ui
+const FRAME_WIDTH: f32 = 18. * 2.;
+const FRAME_HEIGHT: f32 = 18. * 2. + 8. + ui::Header::HEIGHT;
+// room left on the monitor for title bar, task bar and such
+const MONITOR_MARGIN: f32 = 80.;
+const MAX_LENGTH: usize = 4;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum CustomField {
+    Columns,
+    Rows,
+    Mines,
+}
+
+impl CustomField {
+    const ALL: [Self; 3] = [Self::Columns, Self::Rows, Self::Mines];
+
+    #[inline]
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Columns => "columns",
+            Self::Rows => "rows",
+            Self::Mines => "mines",
+        }
+    }
+}
+
+/// Smallest and largest board a custom difficulty may have. The largest one
+/// still fits the window on the monitor.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct CustomLimits {
+    pub min: grid::GridSize,
+    pub max: grid::GridSize,
+}
+
+impl CustomLimits {
+    pub fn new(tile_size: Vec2, monitor: Option<Vec2>) -> Self {
+        let min = Difficulty::Beginner.size();
+        let max = monitor
+            .map(|monitor| grid::GridSize::new(
+                ((monitor.x - FRAME_WIDTH - MONITOR_MARGIN) / tile_size.x).floor().max(0.) as usize,
+                ((monitor.y - FRAME_HEIGHT - MONITOR_MARGIN) / tile_size.y).floor().max(0.) as usize,
+            ))
+            .unwrap_or_else(|| Difficulty::Expert.size());
+
+        Self {
+            min,
+            max: grid::GridSize::new(max.columns().max(min.columns()), max.rows().max(min.rows())),
+        }
+    }
+
+    /// Only the smallest board is limited, for when the monitor is unknown.
+    pub fn unbounded() -> Self {
+        Self {
+            min: Difficulty::Beginner.size(),
+            max: grid::GridSize::new(usize::MAX, usize::MAX),
+        }
+    }
+
+    pub fn check_columns(&self, columns: usize) -> Result<(), DifficultyError> {
+        if columns < self.min.columns() || columns > self.max.columns() {
+            return Err(DifficultyError::ColumnsOutOfRange { min: self.min.columns(), max: self.max.columns() });
+        }
+        Ok(())
+    }
+
+    pub fn check_rows(&self, rows: usize) -> Result<(), DifficultyError> {
+        if rows < self.min.rows() || rows > self.max.rows() {
+            return Err(DifficultyError::RowsOutOfRange { min: self.min.rows(), max: self.max.rows() });
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn check_size(&self, size: grid::GridSize) -> Result<(), DifficultyError> {
+        self.check_columns(size.columns()).and_then(|_| self.check_rows(size.rows()))
+    }
+}
+
+/// Resource
+///
+/// What is typed into the custom difficulty dialog, filled in again every
+/// time it opens.
+pub(crate) struct CustomDialog {
+    values: [String; 3],
+    errors: [Option<String>; 3],
+    active: usize,
+    limits: CustomLimits,
+}
+
+impl Default for CustomDialog {
+    fn default() -> Self {
+        Self::new(&Difficulty::default(), CustomLimits::new((&grid::TileSize::default()).into(), None))
+    }
+}
+
+impl CustomDialog {
+    pub fn new(difficulty: &Difficulty, limits: CustomLimits) -> Self {
+        let size = difficulty.size();
+        Self {
+            values: [
+                size.columns().to_string(),
+                size.rows().to_string(),
+                difficulty.mines().to_string(),
+            ],
+            errors: Default::default(),
+            active: 0,
+            limits,
+        }
+    }
+
+    #[inline]
+    pub fn next(&mut self) {
+        self.active = (self.active + 1) % CustomField::ALL.len();
+    }
+
+    #[inline]
+    pub fn previous(&mut self) {
+        self.active = (self.active + CustomField::ALL.len() - 1) % CustomField::ALL.len();
+    }
+
+    #[inline]
+    pub fn push(&mut self, ch: char) {
+        let value = &mut self.values[self.active];
+        if ch.is_ascii_digit() && value.len() < MAX_LENGTH {
+            value.push(ch);
+        }
+    }
+
+    #[inline]
+    pub fn pop(&mut self) {
+        self.values[self.active].pop();
+    }
+
+    /// The difficulty that was typed in. Problems are kept per field to be
+    /// shown below it.
+    pub fn validate(&mut self, first_click: board::FirstClick) -> Option<Difficulty> {
+        self.errors = Default::default();
+
+        let parsed = self.values.clone().map(|value| value.parse::<usize>().ok());
+        for (i, value) in parsed.iter().enumerate() {
+            if value.is_none() {
+                self.errors[i] = Some("enter a number".to_string());
+            }
+        }
+
+        if let Some(columns) = parsed[0] {
+            self.errors[0] = self.limits.check_columns(columns).err().map(|err| err.to_string());
+        }
+        if let Some(rows) = parsed[1] {
+            self.errors[1] = self.limits.check_rows(rows).err().map(|err| err.to_string());
+        }
+        if self.errors.iter().any(Option::is_some) {
+            return None;
+        }
+
+        // mines can only be checked against a valid size
+        let difficulty = Difficulty::Custom {
+            size: grid::GridSize::new(parsed[0]?, parsed[1]?),
+            mines: parsed[2]?,
+        };
+        if let Err(err) = difficulty.validate(first_click) {
+            self.errors[2] = Some(match err {
+                DifficultyError::TooManyMines { max, .. } => format!("at most {} mines fit", max),
+                err => err.to_string(),
+            });
+            return None;
+        }
+
+        Some(difficulty)
+    }
+
+    pub fn text(&self, font: Handle<Font>, colors: &ui::Colors, cancel: KeyCode) -> Text {
+        let mut lines = ui::OverlayLines::new(font);
+        lines.line("custom difficulty", colors.text).empty();
+
+        for (i, field) in CustomField::ALL.iter().enumerate() {
+            let active = i == self.active;
+            lines.line(format!(
+                "{} {:<8}{}{}",
+                if active { ">" } else { " " },
+                field.label(),
+                self.values[i],
+                if active { "_" } else { "" },
+            ), colors.text);
+
+            match &self.errors[i] {
+                Some(error) => { lines.line(format!("  {}", error), colors.error); }
+                None => { lines.empty(); }
+            }
+        }
+
+        lines
+            .line("tab    next field", colors.dark)
+            .line("enter  start game", colors.dark)
+            .line(format!("{:<7}cancel", format!("{:?}", cancel)), colors.dark);
+        lines.build()
+    }
+}
+
+/// Size of the monitor the game's window is on, when it's known.
+pub(super) fn primary_monitor(winit: &WinitWindows) -> Option<Vec2> {
+    winit.get_window(WindowId::primary())
+        .and_then(|window| window.current_monitor())
+        .map(|monitor| {
+            let size = monitor.size();
+            Vec2::new(size.width as f32, size.height as f32)
+        })
+}
+
+// open the custom difficulty dialog on F4 key press, the game underneath is
+// paused until the dialog is closed
+pub(super) fn open_custom_dialog(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    winit: NonSend<WinitWindows>,
+    tile_size: Res<grid::TileSize>,
+    difficulty: Res<Difficulty>,
+    mut dialog: ResMut<CustomDialog>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !key.just_released(bindings.custom) {
+        return;
+    }
+    match state.current() {
+        GameState::Run | GameState::Over | GameState::Won => {}
+        _ => { return; }
+    }
+
+    let limits = CustomLimits::new(tile_size.deref().into(), primary_monitor(&winit));
+
+    *dialog = CustomDialog::new(&difficulty, limits);
+    let _ = state.push(GameState::Custom);
+}
+
+pub(super) fn spawn_custom_dialog(
+    mut cmd: Commands,
+    asset_server: Res<AssetServer>,
+    colors: Res<ui::Colors>,
+    bindings: Res<Bindings>,
+    dialog: Res<CustomDialog>,
+) {
+    let text = dialog.text(asset_server.get_handle(ui::FONT), &colors, bindings.custom);
+    ui::Overlay::spawn(&mut cmd, colors.overlay, text);
+}
+
+// type digits into the active field, enter starts a game when everything is
+// valid, F4 goes back to the game as it was
+#[allow(clippy::too_many_arguments)]
+pub(super) fn handle_custom_input(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut chars: EventReader<ReceivedCharacter>,
+    first_click: Res<board::FirstClick>,
+    generator: Res<Generator>,
+    mut dialog: ResMut<CustomDialog>,
+    mut difficulty: ResMut<Difficulty>,
+    mut chosen: ResMut<DifficultyChosen>,
+    mut state: ResMut<State<GameState>>,
+) {
+    // opened on release, so the same key press can't close it right away
+    if key.just_pressed(bindings.custom) {
+        chars.iter().for_each(drop);
+        let _ = state.pop();
+        return;
+    }
+
+    for ch in chars.iter() {
+        dialog.push(ch.char);
+    }
+    if key.just_pressed(KeyCode::Back) {
+        dialog.pop();
+    }
+    if key.just_pressed(KeyCode::Tab) || key.just_pressed(KeyCode::Down) {
+        dialog.next();
+    }
+    if key.just_pressed(KeyCode::Up) {
+        dialog.previous();
+    }
+
+    if key.just_pressed(KeyCode::Return) {
+        let first_click = generator.first_click(*first_click);
+        if let Some(set) = dialog.validate(first_click) {
+            if difficulty.change(set, first_click).is_ok() {
+                chosen.0 = true;
+                let _ = state.replace(GameState::Start);
+            }
+        }
+    }
+}
+
+pub(super) fn update_custom_dialog(
+    asset_server: Res<AssetServer>,
+    colors: Res<ui::Colors>,
+    bindings: Res<Bindings>,
+    dialog: Res<CustomDialog>,
+    mut text_query: Query<&mut Text, With<ui::OverlayText>>,
+) {
+    if !dialog.is_changed() {
+        return;
+    }
+
+    for mut text in text_query.iter_mut() {
+        *text = dialog.text(asset_server.get_handle(ui::FONT), &colors, bindings.custom);
+    }
+}
@@ -0,0 +1,194 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::prelude::*;
+use bevy::winit::WinitWindows;
+
+use crate::storage;
+use crate::utils;
+
+use super::*;
+
+// directory in the user's data directory
+const BOARDS_DIR: &str = "boards";
+const POSITIONS_DIR: &str = "positions";
+
+/// What a file dropped on the window turns into.
+enum Loaded {
+    Board(board::Layout),
+    Position(board::Board),
+    Replay(replay::Replay, replay::ReplayInfo),
+}
+
+impl Loaded {
+    #[inline]
+    fn size(&self) -> grid::GridSize {
+        match self {
+            Loaded::Board(layout) => layout.size(),
+            Loaded::Position(position) => position.size(),
+            Loaded::Replay(replay, _) => replay.size(),
+        }
+    }
+}
+
+// the kind of file is told by its extension
+fn load_file(path: &Path) -> Result<Loaded, String> {
+    let extension = path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("mbf") => {
+            let bytes = fs::read(path).map_err(|err| err.to_string())?;
+            let layout = board::read_mbf(&bytes).map_err(|err| err.to_string())?;
+            Ok(Loaded::Board(layout))
+        }
+        Some("txt") => {
+            let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+            let position = board::read_notation(&text).map_err(|err| err.to_string())?;
+            Ok(Loaded::Position(position))
+        }
+        Some("rmv") | Some("avf") => {
+            let (replay, info) = replay::Replay::load(path).map_err(|err| err.to_string())?;
+            Ok(Loaded::Replay(replay, info))
+        }
+        _ => Err("unknown kind of file, boards are .mbf, positions .txt and replays .rmv or .avf".to_string()),
+    }
+}
+
+// start a game on the board or position of a file that is dropped on the
+// window, or play back its replay, as long as the board fits on the monitor
+#[allow(clippy::too_many_arguments)]
+pub(super) fn load_dropped_file(
+    mut drop_events: EventReader<FileDragAndDrop>,
+    winit: NonSend<WinitWindows>,
+    tile_size: Res<grid::TileSize>,
+    mut playback: ResMut<replay::Playback>,
+    mut next_layout: ResMut<NextLayout>,
+    mut next_position: ResMut<NextPosition>,
+    mut notice: ResMut<Notice>,
+    mut state: ResMut<State<GameState>>,
+) {
+    for event in drop_events.iter() {
+        let path = match event {
+            FileDragAndDrop::DroppedFile { path_buf, .. } => path_buf,
+            _ => { continue; }
+        };
+        if state.current().is_dialog() {
+            continue;
+        }
+
+        let limits = custom::CustomLimits::new((&*tile_size).into(), custom::primary_monitor(&winit));
+        let loaded = load_file(path).and_then(|loaded| {
+            limits.check_size(loaded.size()).map_err(|err| err.to_string())?;
+            Ok(loaded)
+        });
+        match loaded {
+            Ok(Loaded::Board(layout)) => {
+                info!("load board {}", path.display());
+                next_layout.0 = Some(layout);
+                let _ = state.set(GameState::Start);
+            }
+            Ok(Loaded::Position(position)) => {
+                info!("load position {}", path.display());
+                next_position.0 = Some(position);
+                let _ = state.set(GameState::Start);
+            }
+            Ok(Loaded::Replay(replay, info)) => match playback.start(replay) {
+                Ok(()) => {
+                    info!("play {}: {}", path.display(), info);
+                    let _ = state.set(GameState::Start);
+                }
+                Err(err) => {
+                    warn!("can't play {}: {}", path.display(), err);
+                    notice.set(format!("can't play replay: {}", err));
+                }
+            },
+            Err(err) => {
+                warn!("can't load {}: {}", path.display(), err);
+                notice.set(format!("can't load file: {}", err));
+            }
+        }
+    }
+}
+
+// write the current board to an MBF file on B key press, once its mines
+// are placed
+pub(super) fn export_board(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    board: Res<board::Board>,
+    state: Res<State<GameState>>,
+    mut notice: ResMut<Notice>,
+) {
+    if !key.just_released(bindings.export_board) || state.current().is_dialog() {
+        return;
+    }
+
+    let layout = match board::Layout::of(&board) {
+        Some(layout) => layout,
+        None => {
+            notice.set("board not exported: no mines yet, reveal a cell first");
+            return;
+        }
+    };
+    let path = match storage::data_file(BOARDS_DIR) {
+        Some(dir) => dir.join(utils::file_name("mbf")),
+        None => {
+            warn!("board not exported: no data directory");
+            notice.set("board not exported");
+            return;
+        }
+    };
+
+    let result = board::write_mbf(&layout)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| {
+            storage::write_atomic(&path, &bytes)
+                .map_err(|err| format!("can't write {}: {}", path.display(), err))
+        });
+    match result {
+        Ok(()) => {
+            info!("board exported to {}", path.display());
+            notice.set(format!("board exported to {}", path.display()));
+        }
+        Err(err) => {
+            warn!("board not exported: {}", err);
+            notice.set("board not exported");
+        }
+    }
+}
+
+// write the current position to a text file on C key press and show its
+// share code, once the mines are placed
+pub(super) fn share_position(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    board: Res<board::Board>,
+    state: Res<State<GameState>>,
+    mut notice: ResMut<Notice>,
+) {
+    if !key.just_released(bindings.share_position) || state.current().is_dialog() {
+        return;
+    }
+    if !board.is_placed() {
+        notice.set("position not shared: no mines yet, reveal a cell first");
+        return;
+    }
+
+    let text = board::write_notation(&board);
+    match storage::data_file(POSITIONS_DIR) {
+        Some(dir) => {
+            let path = dir.join(utils::file_name("txt"));
+            match storage::write_atomic(&path, text.as_bytes()) {
+                Ok(()) => info!("position saved to {}", path.display()),
+                Err(err) => warn!("position not saved: can't write {}: {}", path.display(), err),
+            }
+        }
+        None => warn!("position not saved: no data directory"),
+    }
+
+    let code = board::share_code(&board);
+    info!("share code {}", code);
+    notice.set(format!("share code {}", code));
+}
@@ -1,13 +1,15 @@
 use std::borrow::BorrowMut;
+use std::marker::PhantomData;
 use std::ops::Deref;
 
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::window::WindowId;
 use bevy::winit::WinitWindows;
-use rand::prelude::SliceRandom;
 use winit::dpi::PhysicalSize;
 
 use crate::load::LoadState;
+use crate::settings::Settings;
 use crate::utils;
 
 use super::*;
@@ -22,23 +24,103 @@ pub enum GameState {
     Run,
     // game over
     Over,
+    // all cells without a mine are revealed
+    Won,
+    // custom difficulty dialog is open, the game underneath is paused
+    Custom,
+    // high scores are shown on top of the game
+    HighScores,
+    // a new record is set, asking for the player's name
+    NewRecord,
+    // lifetime statistics are shown on top of the game
+    Stats,
+    // a replay is played back on a read-only board
+    Playback,
+    // asking whether to resume the game saved on the last exit
+    Resume,
+}
+
+impl GameState {
+    /// Whether a dialog or screen is on top of the game, keys that start a
+    /// new game should leave it alone.
+    #[inline]
+    pub fn is_dialog(&self) -> bool {
+        matches!(
+            self,
+            GameState::Custom | GameState::HighScores | GameState::NewRecord | GameState::Stats | GameState::Resume
+        )
+    }
 }
 
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(ui::Colors::default());
+        let settings = app.world.get_resource::<Settings>().cloned().unwrap_or_default();
+        app.insert_resource(settings.colors.clone());
         app.insert_resource(5. as ui::EdgeSize);
         app.insert_resource(8. as ui::EdgePadding);
-        app.insert_resource(grid::TileSize::default());
-        app.insert_resource(Difficulty::default());
+        app.insert_resource(settings.tile_size());
+        let difficulty = settings.difficulty();
+        app.insert_resource(board::Board::new(difficulty.size(), difficulty.mines()));
+        app.insert_resource(difficulty);
+        app.insert_resource(Generator::default());
+        app.insert_resource(board::FirstClick::default());
+        app.insert_resource(QuestionMarks(settings.question_marks));
+        app.insert_resource(settings.bindings.clone());
+        app.insert_resource(Seed::default());
+        app.insert_resource(NextLayout::default());
+        app.insert_resource(DifficultyChosen::default());
+        app.insert_resource(NextPosition::default());
+        app.insert_resource(Unranked::default());
+        app.insert_resource(NoGuess::default());
+        app.insert_resource(Notice::default());
+        app.insert_resource(GameTime::default());
+        app.insert_resource(custom::CustomDialog::default());
+        app.insert_resource(leaderboard::Leaderboard::load());
+        app.insert_resource(leaderboard::NewRecord::default());
+        app.insert_resource(leaderboard::HighScoresView::default());
+        app.insert_resource(stats::Stats::load());
+        app.insert_resource(stats::StatsView::default());
+        app.insert_resource(metrics::Clicks::default());
+        app.insert_resource(replay::Recording::default());
+        app.insert_resource(replay::Playback::default());
+        app.insert_resource(resume::Resume::load());
+        app.insert_resource(hint::Hints::default());
+        app.insert_resource(probability::ProbabilityOverlay::default());
+        app.add_event::<GameWon>();
         app.add_state(GameState::Setup);
 
+        app.add_system(mirror_board);
+        app.add_system(update_mines_counter);
+        app.add_system(update_timer);
+        app.add_system(handle_smiley_click);
+        app.add_system(update_smiley);
+        app.add_system(update_window_title);
+        app.add_system(update_settings);
+        app.add_system(leaderboard::check_record);
+        app.add_system(probability::update_probabilities);
         app.add_system_to_stage(CoreStage::PostUpdate, update_tile_sprite);
+        app.add_system_to_stage(CoreStage::PostUpdate, ui::update_digits_display);
+        app.add_system_to_stage(CoreStage::Last, resume::save_on_exit);
         app.add_system_set(
             SystemSet::on_update(LoadState::Loaded)
                 .with_system(change_difficulty)
+                .with_system(change_first_click)
+                .with_system(toggle_question_marks)
+                .with_system(toggle_no_guess)
+                .with_system(change_generator)
+                .with_system(replay_seed)
+                .with_system(enter_seed)
+                .with_system(custom::open_custom_dialog)
+                .with_system(leaderboard::open_high_scores)
+                .with_system(stats::open_stats)
+                .with_system(metrics::toggle_summary)
+                .with_system(replay::open_last_replay)
+                .with_system(exchange::load_dropped_file)
+                .with_system(exchange::export_board)
+                .with_system(exchange::share_position)
+                .with_system(probability::toggle_probabilities)
         );
 
         app.add_system_set(
@@ -48,18 +130,160 @@ impl Plugin for GamePlugin {
 
         app.add_system_set(
             SystemSet::on_enter(GameState::Start)
-                .with_system(update_grid)
+                .with_system(reset_board)
+                .with_system(reset_game_time)
+                .with_system(reset_notice)
+                .with_system(metrics::reset_clicks)
+                .with_system(replay::reset_recording)
+                .with_system(hint::reset_hints)
+                .with_system(
+                    resume::restore_game
+                        .after(reset_board)
+                        .after(reset_game_time)
+                        .after(reset_notice)
+                        .after(metrics::reset_clicks)
+                        .after(replay::reset_recording)
+                        .after(hint::reset_hints)
+                )
+                .with_system(update_grid.after(resume::restore_game))
                 .with_system(update_ui.after(update_grid))
         );
 
+        app.add_system_set(
+            SystemSet::on_enter(GameState::Run)
+                .with_system(resume::offer_resume)
+        );
+
         app.add_system_set(
             SystemSet::on_update(GameState::Run)
                 .with_system(handle_grid_click)
+                .with_system(tick_game_time)
+                .with_system(hint::show_hint)
+                .with_system(hint::update_hint.after(handle_grid_click))
+        );
+
+        app.add_system_set(
+            SystemSet::on_exit(GameState::Run)
+                .with_system(stats::record_game)
+                .with_system(replay::save_recording)
+                .with_system(hint::hide_hint)
         );
 
         app.add_system_set(
             SystemSet::on_enter(GameState::Over)
                 .with_system(reveal_mines)
+                .with_system(metrics::spawn_summary)
+        );
+
+        app.add_system_set(
+            SystemSet::on_exit(GameState::Over)
+                .with_system(ui::despawn_panel)
+        );
+
+        app.add_system_set(
+            SystemSet::on_enter(GameState::Won)
+                .with_system(win_game)
+                .with_system(metrics::spawn_summary.after(win_game))
+        );
+
+        app.add_system_set(
+            SystemSet::on_exit(GameState::Won)
+                .with_system(ui::despawn_panel)
+        );
+
+        app.add_system_set(
+            SystemSet::on_enter(GameState::Custom)
+                .with_system(custom::spawn_custom_dialog)
+        );
+
+        app.add_system_set(
+            SystemSet::on_update(GameState::Custom)
+                .with_system(custom::handle_custom_input)
+                .with_system(custom::update_custom_dialog.after(custom::handle_custom_input))
+        );
+
+        app.add_system_set(
+            SystemSet::on_exit(GameState::Custom)
+                .with_system(ui::despawn_overlay)
+        );
+
+        app.add_system_set(
+            SystemSet::on_enter(GameState::NewRecord)
+                .with_system(leaderboard::spawn_new_record)
+        );
+
+        app.add_system_set(
+            SystemSet::on_update(GameState::NewRecord)
+                .with_system(leaderboard::handle_name_input)
+                .with_system(leaderboard::update_new_record.after(leaderboard::handle_name_input))
+        );
+
+        app.add_system_set(
+            SystemSet::on_exit(GameState::NewRecord)
+                .with_system(ui::despawn_overlay)
+        );
+
+        app.add_system_set(
+            SystemSet::on_enter(GameState::HighScores)
+                .with_system(leaderboard::spawn_high_scores)
+        );
+
+        app.add_system_set(
+            SystemSet::on_update(GameState::HighScores)
+                .with_system(leaderboard::handle_high_scores_input)
+                .with_system(leaderboard::update_high_scores.after(leaderboard::handle_high_scores_input))
+        );
+
+        app.add_system_set(
+            SystemSet::on_exit(GameState::HighScores)
+                .with_system(ui::despawn_overlay)
+        );
+
+        app.add_system_set(
+            SystemSet::on_enter(GameState::Stats)
+                .with_system(stats::spawn_stats)
+        );
+
+        app.add_system_set(
+            SystemSet::on_update(GameState::Stats)
+                .with_system(stats::handle_stats_input)
+                .with_system(stats::update_stats.after(stats::handle_stats_input))
+        );
+
+        app.add_system_set(
+            SystemSet::on_exit(GameState::Stats)
+                .with_system(ui::despawn_overlay)
+        );
+
+        app.add_system_set(
+            SystemSet::on_enter(GameState::Resume)
+                .with_system(resume::spawn_resume_dialog)
+        );
+
+        app.add_system_set(
+            SystemSet::on_update(GameState::Resume)
+                .with_system(resume::handle_resume_input)
+        );
+
+        app.add_system_set(
+            SystemSet::on_exit(GameState::Resume)
+                .with_system(ui::despawn_overlay)
+        );
+
+        app.add_system_set(
+            SystemSet::on_enter(GameState::Playback)
+                .with_system(replay::spawn_playback)
+        );
+
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playback)
+                .with_system(replay::play)
+                .with_system(replay::update_playback_cursor.after(replay::play))
+        );
+
+        app.add_system_set(
+            SystemSet::on_exit(GameState::Playback)
+                .with_system(replay::despawn_playback)
         );
     }
 }
@@ -69,6 +293,7 @@ fn setup_components(
     mut cmd: Commands,
     ui_colors: Res<ui::Colors>,
     ui_sprites: Res<utils::SpriteSheetBundleBuilder<ui::UiComponent>>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
     difficulty: Res<Difficulty>,
     tile_size: Res<grid::TileSize>,
 ) {
@@ -79,6 +304,13 @@ fn setup_components(
         ui_sprites.deref(),
     );
 
+    // header
+    ui::Header::spawn(
+        cmd.borrow_mut(),
+        texture_atlases.get_handle(ui::DIGITS_TEXTURE_ATLAS),
+        ui_sprites.deref(),
+    );
+
     // grid
     grid::Grid::spawn(
         cmd.borrow_mut(),
@@ -87,17 +319,69 @@ fn setup_components(
     );
 }
 
-// add/remove tiles to grid according to difficulty
+// create a new board according to difficulty, mines are placed on first reveal
+// unless a layout is given for this game, a replay brings its own board and
+// leaves the difficulty as it is
+fn reset_board(
+    playback: Res<replay::Playback>,
+    mut difficulty: ResMut<Difficulty>,
+    mut seed: ResMut<Seed>,
+    mut next_layout: ResMut<NextLayout>,
+    mut next_position: ResMut<NextPosition>,
+    mut unranked: ResMut<Unranked>,
+    mut board: ResMut<board::Board>,
+) {
+    if let Some(start) = playback.pending_board() {
+        *board = start.clone();
+        return;
+    }
+
+    seed.advance();
+    unranked.0 = None;
+    if let Some(position) = next_position.0.take() {
+        seed.clear();
+        *difficulty = Difficulty::Custom {
+            size: position.size(),
+            mines: position.mines(),
+        };
+        *board = position;
+        unranked.0 = Some("loaded positions");
+        return;
+    }
+    if let Some(layout) = next_layout.0.take() {
+        seed.clear();
+        *difficulty = Difficulty::Custom {
+            size: layout.size(),
+            mines: layout.mines().len(),
+        };
+        *board = board::Board::with_layout(&layout);
+        unranked.0 = Some("loaded boards");
+        return;
+    }
+    *board = board::Board::new(difficulty.size(), difficulty.mines());
+}
+
+fn reset_game_time(mut game_time: ResMut<GameTime>) {
+    game_time.reset();
+}
+
+fn reset_notice(mut notice: ResMut<Notice>) {
+    if notice.get().is_some() {
+        notice.clear();
+    }
+}
+
+// add/remove tiles to grid according to the board
 fn update_grid(
     mut cmd: Commands,
-    difficulty: Res<Difficulty>,
+    board: Res<board::Board>,
     tile_size: Res<grid::TileSize>,
     texture_atlases: Res<Assets<TextureAtlas>>,
     mut grid_query: Query<(Entity, &mut grid::Grid)>,
     mut tiles_query: Query<(&mut grid::Tile, &mut Transform)>,
 ) {
     let (grid_entity, mut grid) = grid_query.single_mut();
-    if let grid::ResizeResult::Shrink(entities) = grid.resize(difficulty.size()) {
+    if let grid::ResizeResult::Shrink(entities) = grid.resize(board.size()) {
         cmd.entity(grid_entity).remove_children(entities.as_ref());
         for tile_entity in entities {
             cmd.entity(tile_entity).despawn();
@@ -121,6 +405,7 @@ fn update_grid(
                 let tile_entity = grid::TileBundle::spawn(
                     cmd.borrow_mut(),
                     texture_atlases.get_handle(grid::TILE_TEXTURE_ATLAS),
+                    tile_size,
                     x,
                     y,
                 );
@@ -139,11 +424,16 @@ fn update_grid(
 
 fn update_ui(
     mut state: ResMut<State<GameState>>,
+    playback: Res<replay::Playback>,
     winit: NonSend<WinitWindows>,
+    edge_padding: Res<ui::EdgePadding>,
     mut set: ParamSet<(
         Query<(&grid::Grid, &mut Transform)>,
         Query<(&ui::EdgeSide, &mut Sprite, &mut Transform)>,
         Query<(&ui::EdgeCorner, &mut Transform)>,
+        Query<&mut Transform, With<ui::Header>>,
+        Query<(&ui::DigitDisplay, &mut Transform)>,
+        Query<&mut Transform, With<ui::Smiley>>,
     )>,
 ) {
     let mut grid_query = set.p0();
@@ -151,8 +441,33 @@ fn update_ui(
     grid_transform.translation.x = 18.;
     grid_transform.translation.y = 18.;
 
+    let grid_width = grid.width();
+    let header_x = grid_transform.translation.x;
+    let header_y = grid_transform.translation.y + grid.height() + *edge_padding;
+
     let width = grid.width() + (grid_transform.translation.x * 2.);
-    let height = grid.height() + (grid_transform.translation.y * 2.);
+    let height = header_y + ui::Header::HEIGHT + grid_transform.translation.y;
+
+    let mut header_query = set.p3();
+    let mut header_transform = header_query.single_mut();
+    header_transform.translation.x = header_x;
+    header_transform.translation.y = header_y;
+
+    let mut digits_query = set.p4();
+    for (digit_display, mut digits_transform) in digits_query.iter_mut() {
+        use ui::DigitDisplay;
+        digits_transform.translation.y = f32::floor((ui::Header::HEIGHT - ui::Digits::HEIGHT) * 0.5);
+        match digit_display {
+            DigitDisplay::Mines => {}
+            DigitDisplay::Time => {
+                digits_transform.translation.x = grid_width - ui::Digits::WIDTH;
+            }
+        }
+    }
+
+    let mut smiley_query = set.p5();
+    let mut smiley_transform = smiley_query.single_mut();
+    smiley_transform.translation.x = f32::floor((grid_width - ui::Smiley::SIZE) * 0.5);
 
     let edge_size = 5.;
     let mut edge_sides_query = set.p1();
@@ -193,61 +508,342 @@ fn update_ui(
     // todo: center window once
     utils::center_window(window);
 
-    let _ = state.set(GameState::Run);
+    if playback.is_pending() {
+        let _ = state.set(GameState::Playback);
+    } else {
+        let _ = state.set(GameState::Run);
+    }
 }
 
+enum GridAction {
+    Reveal,
+    Flag,
+    Chord,
+}
+
+// tracks the classic chord, where left and right are held down together
+#[derive(Default)]
+struct ChordButtons {
+    // both buttons are (or were) held down together
+    active: bool,
+    // the chord went off on releasing one of the buttons
+    fired: bool,
+}
+
+// mouse buttons and the grid under the cursor
+#[derive(SystemParam)]
+struct GridInput<'w, 's> {
+    windows: Res<'w, Windows>,
+    mouse_button: Res<'w, Input<MouseButton>>,
+    chord: Local<'s, ChordButtons>,
+    grid_query: Query<'w, 's, (&'static grid::Grid, &'static Transform)>,
+}
+
+// settings that decide how a click on the grid plays out
+#[derive(SystemParam)]
+struct ClickSettings<'w, 's> {
+    difficulty: Res<'w, Difficulty>,
+    generator: Res<'w, Generator>,
+    first_click: Res<'w, board::FirstClick>,
+    no_guess: Res<'w, NoGuess>,
+    question_marks: Res<'w, QuestionMarks>,
+    seed: Res<'w, Seed>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+// what a click on the grid is counted and recorded in
+#[derive(SystemParam)]
+struct ClickRecord<'w, 's> {
+    game_time: Res<'w, GameTime>,
+    clicks: ResMut<'w, metrics::Clicks>,
+    recording: ResMut<'w, replay::Recording>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+// left reveals on release, right flags (or marks) on press, middle or left and right
+// together chord on release
 fn handle_grid_click(
     mut state: ResMut<State<GameState>>,
-    windows: Res<Windows>,
-    mouse_button: Res<Input<MouseButton>>,
-    grid_query: Query<(&grid::Grid, &Transform)>,
-    mut tiles_query: Query<&mut grid::Tile>,
+    input: GridInput,
+    settings: ClickSettings,
+    mut board: ResMut<board::Board>,
+    mut notice: ResMut<Notice>,
+    record: ClickRecord,
 ) {
+    let GridInput { windows, mouse_button, mut chord, grid_query } = input;
+    let ClickRecord { game_time, mut clicks, mut recording, .. } = record;
+    if mouse_button.pressed(MouseButton::Left) && mouse_button.pressed(MouseButton::Right) {
+        chord.active = true;
+    }
+
     #[allow(unused_assignments)]
-        let mut btn: Option<MouseButton> = None;
-    if mouse_button.just_pressed(MouseButton::Left) {
-        btn = Some(MouseButton::Left);
+        let mut action: Option<(GridAction, replay::ReplayButton)> = None;
+    if chord.active {
+        if !chord.fired && (mouse_button.just_released(MouseButton::Left) || mouse_button.just_released(MouseButton::Right)) {
+            chord.fired = true;
+            action = Some((GridAction::Chord, replay::ReplayButton::Both));
+        }
+        if !mouse_button.pressed(MouseButton::Left) && !mouse_button.pressed(MouseButton::Right) {
+            *chord = ChordButtons::default();
+        }
+    } else if mouse_button.just_released(MouseButton::Left) {
+        action = Some((GridAction::Reveal, replay::ReplayButton::Left));
     } else if mouse_button.just_pressed(MouseButton::Right) {
-        btn = Some(MouseButton::Right);
-    } else {
-        return;
+        action = Some((GridAction::Flag, replay::ReplayButton::Right));
+    } else if mouse_button.just_released(MouseButton::Middle) {
+        action = Some((GridAction::Chord, replay::ReplayButton::Middle));
     }
 
+    let (action, button) = match action {
+        Some(action) => action,
+        None => { return; }
+    };
+
     windows.get_primary()
         .and_then(|window| {
             window.cursor_position()
         })
         .and_then(|cursor_position| {
             let (grid, grid_transform) = grid_query.single();
-            grid.get_tile_xy(
+            grid.get_position_xy(
                 cursor_position.x - grid_transform.translation.x,
                 cursor_position.y - grid_transform.translation.y,
             )
         })
-        .and_then(|entity| {
-            tiles_query.get_mut(entity).ok()
-        })
-        .map(|mut tile| {
-            let tile = &mut *tile;
-
-            use grid::Tile;
-            match btn.unwrap() {
-                MouseButton::Left => {
-                    tile.change(Tile::Boom);
-                    let _ = state.set(GameState::Over);
+        .map(|(col, row)| {
+            let index = board.size().index_of(col, row);
+            recording.begin(&board, settings.question_marks.0);
+            let result = match action {
+                GridAction::Reveal => {
+                    if !board.is_placed() && board.cell_at(index).is_covered()
+                        && !place_mines(&mut board, index, &settings) {
+                        notice.set("no-guess board not found, may need a guess");
+                    }
+                    clicks.left += 1;
+                    recording.push(game_time.elapsed(), (col, row), button, replay::ReplayAction::Reveal);
+                    board.reveal(index)
+                }
+                GridAction::Chord => {
+                    clicks.chord += 1;
+                    recording.push(game_time.elapsed(), (col, row), button, replay::ReplayAction::Chord);
+                    board.chord(index)
                 }
-                MouseButton::Right => {
-                    match tile {
-                        Tile::Default => { tile.change(Tile::Flag); }
-                        Tile::Flag => { tile.change(Tile::Default); }
-                        _ => {}
+                GridAction::Flag => {
+                    clicks.right += 1;
+                    recording.push(game_time.elapsed(), (col, row), button, replay::ReplayAction::Flag);
+                    if !board.cycle_mark(index, settings.question_marks.0) {
+                        clicks.wasted += 1;
                     }
+                    return;
                 }
-                _ => {}
+            };
+
+            if let board::RevealResult::None = result {
+                clicks.wasted += 1;
+            }
+
+            if let board::RevealResult::Exploded = result {
+                let _ = state.set(GameState::Over);
+            } else if board.is_cleared() {
+                let _ = state.set(GameState::Won);
             }
         });
 }
 
+// place the mines once the first cell to reveal is known, false when a
+// no-guess board was asked for and not found
+fn place_mines(board: &mut board::Board, index: usize, settings: &ClickSettings) -> bool {
+    let mut rng = board::seeded_rng(settings.seed.current().unwrap_or_default());
+    let generator = if settings.no_guess.enabled(&settings.difficulty) {
+        settings.no_guess.generator()
+    } else {
+        settings.generator.0.as_ref()
+    };
+    board.generate(generator, index, *settings.first_click, &mut rng);
+    !generator.no_guess() || board::is_solvable(board, index)
+}
+
+// the clock only runs once the first cell is revealed
+fn tick_game_time(
+    time: Res<Time>,
+    board: Res<board::Board>,
+    mut game_time: ResMut<GameTime>,
+) {
+    if board.revealed() > 0 {
+        game_time.tick(time.delta());
+    }
+}
+
+// mines left according to the placed flags, goes negative when there are
+// more flags than mines
+fn update_mines_counter(
+    board: Res<board::Board>,
+    mut digits_query: Query<(&ui::DigitDisplay, &mut ui::Digits)>,
+) {
+    let mines = board.mines() as i32 - board.flags() as i32;
+    for (digit_display, mut digits) in digits_query.iter_mut() {
+        if let ui::DigitDisplay::Mines = digit_display {
+            if digits.value != mines {
+                digits.value = mines;
+            }
+        }
+    }
+}
+
+fn update_timer(
+    game_time: Res<GameTime>,
+    mut digits_query: Query<(&ui::DigitDisplay, &mut ui::Digits)>,
+) {
+    let seconds = game_time.elapsed().as_secs().min(999) as i32;
+    for (digit_display, mut digits) in digits_query.iter_mut() {
+        if let ui::DigitDisplay::Time = digit_display {
+            if digits.value != seconds {
+                digits.value = seconds;
+            }
+        }
+    }
+}
+
+// start a new game when the smiley is clicked
+fn handle_smiley_click(
+    mut state: ResMut<State<GameState>>,
+    windows: Res<Windows>,
+    mouse_button: Res<Input<MouseButton>>,
+    mut smiley_query: Query<(&mut ui::Smiley, &GlobalTransform)>,
+) {
+    if state.current().is_dialog() {
+        return;
+    }
+
+    let (mut smiley, smiley_transform) = match smiley_query.get_single_mut() {
+        Ok(smiley) => smiley,
+        Err(_) => { return; }
+    };
+
+    let hover = windows.get_primary()
+        .and_then(|window| window.cursor_position())
+        .map(|cursor_position| ui::Smiley::contains(smiley_transform, cursor_position))
+        .unwrap_or(false);
+
+    if mouse_button.just_pressed(MouseButton::Left) && hover {
+        smiley.pressed = true;
+    } else if smiley.pressed && mouse_button.just_released(MouseButton::Left) {
+        smiley.pressed = false;
+        if hover {
+            let _ = state.set(GameState::Start);
+        }
+    }
+}
+
+// show the smiley's pressed state and the face that fits the game
+fn update_smiley(
+    state: Res<State<GameState>>,
+    windows: Res<Windows>,
+    mouse_button: Res<Input<MouseButton>>,
+    ui_sprites: Option<Res<utils::SpriteSheetBundleBuilder<ui::UiComponent>>>,
+    grid_query: Query<(&grid::Grid, &Transform)>,
+    mut smiley_query: Query<(&ui::Smiley, &mut TextureAtlasSprite), Without<ui::Face>>,
+    mut face_query: Query<(&mut ui::Face, &mut TextureAtlasSprite, &mut Transform), Without<grid::Grid>>,
+) {
+    let ui_sprites = match ui_sprites {
+        Some(ui_sprites) => ui_sprites,
+        None => { return; }
+    };
+    let (smiley, mut button_sprite) = match smiley_query.get_single_mut() {
+        Ok(smiley) => smiley,
+        Err(_) => { return; }
+    };
+
+    let button = if smiley.pressed {
+        ui::UiComponent::SmileyButtonPressed
+    } else {
+        ui::UiComponent::SmileyButton
+    };
+    if let Some(index) = ui_sprites.index(button) {
+        if button_sprite.index != index {
+            button_sprite.index = index;
+        }
+    }
+
+    let face = match state.current() {
+        GameState::Over => ui::Face::Dead,
+        GameState::Won => ui::Face::Cool,
+        GameState::Run if !smiley.pressed && is_tile_held(&windows, &mouse_button, &grid_query) => {
+            ui::Face::Surprised
+        }
+        _ => ui::Face::Happy,
+    };
+
+    if let Ok((mut face_component, mut face_sprite, mut face_transform)) = face_query.get_mut(smiley.face()) {
+        if *face_component != face {
+            *face_component = face;
+        }
+        if let Some(index) = ui_sprites.index(face.ui_component()) {
+            if face_sprite.index != index {
+                face_sprite.index = index;
+            }
+        }
+
+        let offset = smiley.face_offset();
+        if face_transform.translation.truncate() != offset {
+            face_transform.translation.x = offset.x;
+            face_transform.translation.y = offset.y;
+        }
+    }
+}
+
+// a button that reveals is held down above one of the grid's tiles
+fn is_tile_held(
+    windows: &Windows,
+    mouse_button: &Input<MouseButton>,
+    grid_query: &Query<(&grid::Grid, &Transform)>,
+) -> bool {
+    if !mouse_button.pressed(MouseButton::Left) && !mouse_button.pressed(MouseButton::Middle) {
+        return false;
+    }
+
+    windows.get_primary()
+        .and_then(|window| window.cursor_position())
+        .and_then(|cursor_position| {
+            let (grid, grid_transform) = grid_query.get_single().ok()?;
+            grid.get_position_xy(
+                cursor_position.x - grid_transform.translation.x,
+                cursor_position.y - grid_transform.translation.y,
+            )
+        })
+        .is_some()
+}
+
+// update the grid's tiles whenever the board changes
+fn mirror_board(
+    board: Res<board::Board>,
+    grid_query: Query<&grid::Grid>,
+    mut tiles_query: Query<&mut grid::Tile>,
+) {
+    if !board.is_changed() {
+        return;
+    }
+
+    let grid = match grid_query.get_single() {
+        Ok(grid) => grid,
+        Err(_) => { return; }
+    };
+
+    for (index, tile_entity) in grid.iter() {
+        if index >= board.size().capacity() {
+            continue;
+        }
+        if let Ok(mut tile) = tiles_query.get_mut(tile_entity) {
+            let want = grid::Tile::mirror(board.deref(), index);
+            if *tile != want {
+                tile.change(want);
+            }
+        }
+    }
+}
+
 // make sure a tile's sprite is updated according to it's kind
 fn update_tile_sprite(
     mut tiles_query: Query<(&grid::Tile, &mut TextureAtlasSprite), Changed<grid::Tile>>
@@ -260,56 +856,310 @@ fn update_tile_sprite(
     }
 }
 
-// reveal mines when the game ends
+// reveal the actual mines when the game is lost, the tiles cross out any
+// wrongly placed flags
 fn reveal_mines(
+    mut board: ResMut<board::Board>,
+) {
+    board.reveal_mines();
+}
+
+// flag the remaining mines and let everyone know the game is won
+fn win_game(
     difficulty: Res<Difficulty>,
-    mut tiles_query: Query<(Entity, &mut grid::Tile)>,
+    seed: Res<Seed>,
+    unranked: Res<Unranked>,
+    game_time: Res<GameTime>,
+    mut board: ResMut<board::Board>,
+    mut won_event: EventWriter<GameWon>,
+) {
+    board.flag_mines();
+    won_event.send(GameWon {
+        time: game_time.elapsed(),
+        difficulty: difficulty.clone(),
+        bbbv: board.bbbv(),
+        seed: seed.current(),
+        unranked: unranked.0,
+    });
+}
+
+// change difficulty on F1/F2/F3 key press
+#[allow(clippy::too_many_arguments)]
+fn change_difficulty(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    first_click: Res<board::FirstClick>,
+    generator: Res<Generator>,
+    mut difficulty: ResMut<Difficulty>,
+    mut chosen: ResMut<DifficultyChosen>,
+    mut notice: ResMut<Notice>,
+    mut state: ResMut<State<GameState>>,
 ) {
-    let mut want = difficulty.mines();
-    let mut remaining_tiles = Vec::<Entity>::with_capacity(tiles_query.iter().len());
+    if state.current().is_dialog() {
+        return;
+    }
 
-    for (tile_entity, mut tile) in tiles_query.iter_mut() {
-        let tile = &mut *tile;
+    let first_click = generator.first_click(*first_click);
+    let result = if key.just_released(bindings.beginner) {
+        difficulty.change(Difficulty::Beginner, first_click)
+    } else if key.just_released(bindings.intermediate) {
+        difficulty.change(Difficulty::Intermediate, first_click)
+    } else if key.just_released(bindings.expert) {
+        difficulty.change(Difficulty::Expert, first_click)
+    } else {
+        return;
+    };
 
-        use grid::Tile;
-        match tile {
-            Tile::Boom => {
-                want -= 1;
-            }
-            Tile::Mine => {
-                want -= 1;
-                tile.change(Tile::Boom);
-            }
-            _ => {
-                remaining_tiles.push(tile_entity);
-            }
+    if let Err(err) = result {
+        notice.set(err.to_string());
+        return;
+    }
+
+    chosen.0 = true;
+    let _ = state.set(GameState::Start);
+}
+
+// cycle through first click guarantees on F5 key press, applies as soon as
+// mines are placed
+fn change_first_click(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    state: Res<State<GameState>>,
+    difficulty: Res<Difficulty>,
+    generator: Res<Generator>,
+    mut first_click: ResMut<board::FirstClick>,
+    mut notice: ResMut<Notice>,
+) {
+    if !key.just_released(bindings.first_click) || state.current().is_dialog() {
+        return;
+    }
+
+    let set = first_click.next();
+    if let Err(err) = difficulty.validate(generator.first_click(set)) {
+        notice.set(err.to_string());
+        return;
+    }
+
+    notice.set(format!("first click {:?}", set));
+    *first_click = set;
+}
+
+// turn question marks on or off on F6 key press
+fn toggle_question_marks(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    state: Res<State<GameState>>,
+    mut question_marks: ResMut<QuestionMarks>,
+    mut notice: ResMut<Notice>,
+) {
+    if key.just_released(bindings.question_marks) && !state.current().is_dialog() {
+        question_marks.0 = !question_marks.0;
+        notice.set(format!("question marks {}", if question_marks.0 { "on" } else { "off" }));
+    }
+}
+
+// turn no-guess boards on or off for the current difficulty on F8 key press,
+// applies as soon as mines are placed
+fn toggle_no_guess(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    state: Res<State<GameState>>,
+    difficulty: Res<Difficulty>,
+    mut no_guess: ResMut<NoGuess>,
+    mut notice: ResMut<Notice>,
+) {
+    if key.just_released(bindings.no_guess) && !state.current().is_dialog() {
+        let enabled = no_guess.toggle(&difficulty);
+        notice.set(format!("no-guess boards for {:?} {}", *difficulty, if enabled { "on" } else { "off" }));
+    }
+}
+
+// switch to the next mine generator on F9 key press, skipping the ones the
+// difficulty has too many mines for, applies as soon as mines are placed
+fn change_generator(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    state: Res<State<GameState>>,
+    difficulty: Res<Difficulty>,
+    first_click: Res<board::FirstClick>,
+    mut generator: ResMut<Generator>,
+    mut notice: ResMut<Notice>,
+) {
+    if !key.just_released(bindings.generator) || state.current().is_dialog() {
+        return;
+    }
+
+    // the uniform generator always fits, so this ends
+    loop {
+        generator.next();
+        match difficulty.validate(generator.first_click(*first_click)) {
+            Ok(()) => { break; }
+            Err(err) => info!("skip generator {}: {}", generator.0.name(), err),
         }
     }
+    notice.set(format!("generator {}", generator.0.name()));
+}
 
-    remaining_tiles.shuffle(&mut rand::thread_rng());
-    remaining_tiles.truncate(want);
+// start a new game with the same seed on F7 key press
+fn replay_seed(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut seed: ResMut<Seed>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !key.just_released(bindings.replay_seed) || state.current().is_dialog() {
+        return;
+    }
 
-    for tile in remaining_tiles {
-        let (_, mut tile) = tiles_query.get_mut(tile).unwrap();
-        tile.change(grid::Tile::Mine);
+    // a board from a layout has no seed to replay
+    if let Some(current) = seed.current() {
+        seed.set_next(current);
+        let _ = state.set(GameState::Start);
     }
 }
 
-// change difficulty on F1/F2/F3 key press
-fn change_difficulty(
+// press S, type a seed and press enter to start a game with that seed
+fn enter_seed(
     key: Res<Input<KeyCode>>,
-    mut difficulty: ResMut<Difficulty>,
+    bindings: Res<Bindings>,
+    mut chars: EventReader<ReceivedCharacter>,
+    mut windows: ResMut<Windows>,
+    mut seed: ResMut<Seed>,
     mut state: ResMut<State<GameState>>,
+    mut input: Local<Option<String>>,
 ) {
-    if key.just_released(KeyCode::F1) {
-        difficulty.change(Difficulty::Beginner);
-    } else if key.just_released(KeyCode::F2) {
-        difficulty.change(Difficulty::Intermediate);
-    } else if key.just_released(KeyCode::F3) {
-        difficulty.change(Difficulty::Expert);
-    } else {
+    let text = match input.as_mut() {
+        Some(text) => text,
+        None => {
+            chars.iter().for_each(drop);
+            if key.just_released(bindings.enter_seed) && !state.current().is_dialog() {
+                *input = Some(String::new());
+                set_window_title(&mut windows, "enter seed: _");
+            }
+            return;
+        }
+    };
+
+    for ch in chars.iter() {
+        if ch.char.is_ascii_digit() && text.len() < 20 {
+            text.push(ch.char);
+        }
+    }
+    if key.just_pressed(KeyCode::Back) {
+        text.pop();
+    }
+
+    if key.just_pressed(KeyCode::Return) {
+        match text.parse::<u64>() {
+            Ok(value) => {
+                seed.set_next(value);
+                let _ = state.set(GameState::Start);
+            }
+            Err(_) => { seed.set_changed(); }
+        }
+        *input = None;
         return;
     }
 
-    let _ = state.set(GameState::Start);
-}
\ No newline at end of file
+    let title = format!("enter seed: {}_", text);
+    set_window_title(&mut windows, &title);
+}
+
+// write settings that are changed in the game back to the settings file
+fn update_settings(
+    difficulty: Res<Difficulty>,
+    question_marks: Res<QuestionMarks>,
+    mut chosen: ResMut<DifficultyChosen>,
+    mut notice: ResMut<Notice>,
+    settings: Option<ResMut<Settings>>,
+) {
+    let mut settings = match settings {
+        Some(settings) => settings,
+        None => { return; }
+    };
+    // resources that are just added come from the settings or the command
+    // line, the difficulty only counts when the player picked it
+    let chose_difficulty = std::mem::take(&mut chosen.0);
+    let changed = chose_difficulty || (question_marks.is_changed() && !question_marks.is_added());
+    if !changed {
+        return;
+    }
+
+    let mut set = settings.clone();
+    if chose_difficulty {
+        set.set_difficulty(&difficulty);
+    }
+    set.question_marks = question_marks.0;
+    if set == *settings {
+        return;
+    }
+
+    *settings = set;
+    if let Err(err) = settings.save() {
+        warn!("settings not saved: {}", err);
+        notice.set("settings not saved");
+    }
+}
+
+// show the seed of the current game, followed by the notice if there is one
+fn update_window_title(
+    seed: Res<Seed>,
+    notice: Res<Notice>,
+    mut windows: ResMut<Windows>,
+) {
+    if !seed.is_changed() && !notice.is_changed() {
+        return;
+    }
+
+    let title = match seed.current() {
+        Some(current) => format!("seed {}", current),
+        None => "custom board".to_string(),
+    };
+    let title = match notice.get() {
+        Some(text) => format!("{} - {}", title, text),
+        None => title,
+    };
+    set_window_title(&mut windows, &title);
+}
+
+#[inline]
+pub(crate) fn set_window_title(windows: &mut Windows, text: &str) {
+    if let Some(window) = windows.get_primary_mut() {
+        let title = format!("Minesweeper - {}", text);
+        if window.title() != title {
+            window.set_title(title);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn playback_keeps_difficulty() {
+        let size = Difficulty::Beginner.size();
+        let mut played = board::Board::new(size, 10);
+        played.place_mines(&(0..10).collect::<Vec<_>>());
+        let mut playback = replay::Playback::default();
+        playback.start(replay::Replay::new(&played, None, replay::ReplayStart::default(), Vec::new())).unwrap();
+
+        let mut world = World::new();
+        world.insert_resource(playback);
+        world.insert_resource(Difficulty::Expert);
+        world.insert_resource(Seed::default());
+        world.insert_resource(NextLayout::default());
+        world.insert_resource(NextPosition::default());
+        world.insert_resource(Unranked::default());
+        world.insert_resource(board::Board::new(Difficulty::Expert.size(), Difficulty::Expert.mines()));
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(reset_board);
+        stage.run(&mut world);
+
+        assert!(matches!(*world.resource::<Difficulty>(), Difficulty::Expert));
+        let board = world.resource::<board::Board>();
+        assert_eq!((board.size().columns(), board.size().rows()), (size.columns(), size.rows()));
+        assert_eq!(board.mines(), 10);
+    }
+}
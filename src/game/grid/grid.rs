@@ -81,15 +81,28 @@ impl Grid {
         self.tiles[index]
     }
 
+    /// Column and row of the tile at the given position, relative to the grid.
     #[inline]
-    pub fn get_tile_xy(&self, x: f32, y: f32) -> Option<Entity> {
+    pub fn get_position_xy(&self, x: f32, y: f32) -> Option<(usize, usize)> {
         if x < 0. || y < 0. {
             return None;
         }
 
-        self.get_tile(
-            f32::floor(x / self.tile_size.x) as usize,
-            f32::floor(y / self.tile_size.y) as usize,
-        )
+        let col = f32::floor(x / self.tile_size.x) as usize;
+        let row = f32::floor(y / self.tile_size.y) as usize;
+        if col >= self.grid_size.columns() || row >= self.grid_size.rows() {
+            return None;
+        }
+
+        Some((col, row))
+    }
+
+    /// Iterate over all tile entities together with their index.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item=(usize, Entity)> + '_ {
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entity)| Some((index, (*entity)?)))
     }
 }
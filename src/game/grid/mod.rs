@@ -25,4 +25,27 @@ impl GridSize {
     pub fn index_of(&self, col: usize, row: usize) -> usize {
         col + (row * self.columns())
     }
+
+    #[inline(always)]
+    pub fn position_of(&self, index: usize) -> (usize, usize) {
+        (index % self.columns(), index / self.columns())
+    }
+
+    /// Indexes of the (up to) 8 cells surrounding the cell at index.
+    pub fn neighbours(&self, index: usize) -> impl Iterator<Item=usize> {
+        let size = *self;
+        let (col, row) = self.position_of(index);
+
+        (-1isize..=1)
+            .flat_map(|dr| (-1isize..=1).map(move |dc| (dc, dr)))
+            .filter(|&(dc, dr)| dc != 0 || dr != 0)
+            .filter_map(move |(dc, dr)| {
+                let c = col as isize + dc;
+                let r = row as isize + dr;
+                if c < 0 || r < 0 || c as usize >= size.columns() || r as usize >= size.rows() {
+                    return None;
+                }
+                Some(size.index_of(c as usize, r as usize))
+            })
+    }
 }
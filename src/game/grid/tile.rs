@@ -3,19 +3,26 @@ use std::hash::{Hash, Hasher};
 use bevy::prelude::*;
 use bevy::sprite::Anchor;
 
+use crate::game::board::Board;
+
 pub const TILE_TEXTURE_ATLAS: &str = "tiles";
 
 /// Tile size resource
 pub struct TileSize(Vec2);
 
 impl TileSize {
+    /// Size of a tile in the texture, tiles are scaled from it.
+    pub const TEXTURE: f32 = 24.;
+    pub const MIN: u32 = 8;
+    pub const MAX: u32 = 96;
+
     pub fn new(size: f32) -> Self {
         Self { 0: Vec2::splat(size) }
     }
 }
 
 impl Default for TileSize {
-    fn default() -> Self { Self::new(24.) }
+    fn default() -> Self { Self::new(Self::TEXTURE) }
 }
 
 impl From<&TileSize> for Vec2 {
@@ -28,13 +35,24 @@ pub enum Tile {
     Flag,
     Mine,
     Boom,
+    Empty,
+    One,
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Question,
+    WrongFlag,
 }
 
 impl Tile {
     #[inline(always)]
-    pub fn all() -> [Self; 4] {
+    pub fn all() -> [Self; 15] {
         use Tile::*;
-        [Default, Flag, Mine, Boom]
+        [Default, Flag, Mine, Boom, Empty, One, Two, Three, Four, Five, Six, Seven, Eight, Question, WrongFlag]
     }
 
     #[inline]
@@ -45,9 +63,63 @@ impl Tile {
             Flag => 1,
             Mine => 2,
             Boom => 3,
+            Empty => 4,
+            One => 5,
+            Two => 6,
+            Three => 7,
+            Four => 8,
+            Five => 9,
+            Six => 10,
+            Seven => 11,
+            Eight => 12,
+            Question => 13,
+            WrongFlag => 14,
         };
     }
 
+    /// Revealed tile showing the amount of adjacent mines.
+    #[inline]
+    pub fn number(adjacent: u8) -> Self {
+        use Tile::*;
+        match adjacent {
+            0 => Empty,
+            1 => One,
+            2 => Two,
+            3 => Three,
+            4 => Four,
+            5 => Five,
+            6 => Six,
+            7 => Seven,
+            _ => Eight,
+        }
+    }
+
+    /// Tile that represents the cell at index of the board.
+    pub fn mirror(board: &Board, index: usize) -> Self {
+        use Tile::*;
+        let cell = board.cell_at(index);
+        if cell.is_flagged() {
+            // once the game is lost, flags without a mine are crossed out
+            if board.is_lost() && !cell.is_mine() {
+                return WrongFlag;
+            }
+            return Flag;
+        }
+        if cell.is_question() {
+            return Question;
+        }
+        if !cell.is_revealed() {
+            return Default;
+        }
+        if board.exploded() == Some(index) {
+            return Boom;
+        }
+        if cell.is_mine() {
+            return Mine;
+        }
+        Self::number(cell.adjacent())
+    }
+
     #[inline(always)]
     pub fn change(&mut self, set: Self) {
         *self = set;
@@ -70,9 +142,10 @@ pub struct TileBundle {
 
 impl TileBundle {
     #[inline]
-    pub fn spawn(cmd: &mut Commands, texture_atlas: Handle<TextureAtlas>, x: f32, y: f32) -> Entity {
+    pub fn spawn(cmd: &mut Commands, texture_atlas: Handle<TextureAtlas>, size: Vec2, x: f32, y: f32) -> Entity {
         let mut sprite = TextureAtlasSprite::new(Tile::Default.index());
         sprite.anchor = Anchor::BottomLeft;
+        sprite.custom_size = Some(size);
 
         cmd.spawn_bundle(Self {
             tile: Tile::Default,
@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use super::*;
+
+/// Resource
+///
+/// Hints asked for in the current game, and the cell that is shown.
+#[derive(Default)]
+pub(crate) struct Hints {
+    used: u32,
+    cell: Option<usize>,
+}
+
+impl Hints {
+    #[inline(always)]
+    pub fn used(&self) -> u32 { self.used }
+
+    /// Hints of a game that is resumed.
+    #[inline(always)]
+    pub fn restore(&mut self, used: u32) { self.used = used; }
+}
+
+/// Tint on top of the tile of the hinted cell, a child of the tile.
+#[derive(Component)]
+pub(crate) struct HintHighlight;
+
+pub(super) fn reset_hints(
+    mut cmd: Commands,
+    mut hints: ResMut<Hints>,
+    highlight_query: Query<Entity, With<HintHighlight>>,
+) {
+    *hints = Hints::default();
+    despawn_highlights(&mut cmd, &highlight_query);
+}
+
+// highlight a cell that is provably safe or a mine on G key press, a hint
+// makes the game unranked
+#[allow(clippy::too_many_arguments)]
+pub(super) fn show_hint(
+    mut cmd: Commands,
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    colors: Res<ui::Colors>,
+    tile_size: Res<grid::TileSize>,
+    board: Res<board::Board>,
+    mut hints: ResMut<Hints>,
+    mut unranked: ResMut<Unranked>,
+    mut notice: ResMut<Notice>,
+    grid_query: Query<&grid::Grid>,
+) {
+    if !key.just_released(bindings.hint) {
+        return;
+    }
+    // the one that is shown still stands
+    if hints.cell.is_some() {
+        return;
+    }
+    if !board.is_placed() {
+        notice.set("no hint yet, reveal a cell first");
+        return;
+    }
+
+    let (index, knowledge) = match board::find_hint(&board) {
+        Some(hint) => hint,
+        None => {
+            notice.set("no cell can be proven safe or a mine, it takes a guess");
+            return;
+        }
+    };
+    let (col, row) = board.size().position_of(index);
+    let tile_entity = match grid_query.get_single().ok().and_then(|grid| grid.get_tile(col, row)) {
+        Some(tile_entity) => tile_entity,
+        None => { return; }
+    };
+
+    hints.used += 1;
+    hints.cell = Some(index);
+    unranked.0 = Some("games with hints");
+
+    let (mut color, what) = match knowledge {
+        board::Knowledge::Mine => (colors.error, "a mine"),
+        _ => (colors.highlight, "safe"),
+    };
+    color.set_a(0.4);
+    info!("hint {}: cell {},{} is {}", hints.used, col, row, what);
+
+    let tile_size: Vec2 = (&*tile_size).into();
+    let highlight = cmd.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color,
+            custom_size: Some(tile_size),
+            anchor: Anchor::BottomLeft,
+            ..default()
+        },
+        transform: Transform::from_xyz(0., 0., 1.),
+        ..default()
+    })
+        .insert(HintHighlight)
+        .id();
+    cmd.entity(tile_entity).add_child(highlight);
+}
+
+// the highlight goes once the hinted cell is revealed or flagged
+pub(super) fn update_hint(
+    mut cmd: Commands,
+    board: Res<board::Board>,
+    mut hints: ResMut<Hints>,
+    highlight_query: Query<Entity, With<HintHighlight>>,
+) {
+    let index = match hints.cell {
+        Some(index) => index,
+        None => { return; }
+    };
+    let cell = board.cell_at(index);
+    if cell.is_revealed() || (cell.is_flagged() && cell.is_mine()) {
+        hints.cell = None;
+        despawn_highlights(&mut cmd, &highlight_query);
+    }
+}
+
+// the count stays for the game's stats
+pub(super) fn hide_hint(
+    mut cmd: Commands,
+    mut hints: ResMut<Hints>,
+    highlight_query: Query<Entity, With<HintHighlight>>,
+) {
+    hints.cell = None;
+    despawn_highlights(&mut cmd, &highlight_query);
+}
+
+#[inline]
+fn despawn_highlights(cmd: &mut Commands, highlight_query: &Query<Entity, With<HintHighlight>>) {
+    for entity in highlight_query.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Settings;
+use crate::storage;
+use crate::utils;
+
+use super::*;
+
+const FILE: &str = "leaderboard.ron";
+const NAME_LENGTH: usize = 16;
+// name column of the high score view
+const NAME_WIDTH: usize = 10;
+
+/// Amount of records kept per board.
+pub(crate) const RECORDS: usize = 10;
+
+/// A won game that made it onto the leaderboard.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Record {
+    pub name: String,
+    pub date: String,
+    pub time_ms: u64,
+    pub bbbv: usize,
+    pub seed: Option<u64>,
+}
+
+/// Resource
+///
+/// Best times per preset and per custom size and mines, kept in the user's
+/// data directory.
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct Leaderboard {
+    boards: BTreeMap<String, Vec<Record>>,
+}
+
+impl Leaderboard {
+    #[inline]
+    fn path() -> Option<PathBuf> {
+        storage::data_file(FILE)
+    }
+
+    /// Read the leaderboard file. A file that can't be read is moved aside,
+    /// so the next record doesn't overwrite it.
+    pub fn load() -> Self {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => { return Self::default(); }
+        };
+
+        match storage::read_ron::<Self>(&path) {
+            Ok(leaderboard) => leaderboard.unwrap_or_default(),
+            Err(err) => {
+                let backup = path.with_extension("ron.bak");
+                warn!("leaderboard {} can't be read: {}, moved to {}", path.display(), err, backup.display());
+                let _ = fs::rename(&path, &backup);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or_else(|| "no data directory".to_string())?;
+        storage::write_ron(&path, self)
+    }
+
+    /// Records of a board, fastest first.
+    #[inline]
+    pub fn records(&self, board: &str) -> &[Record] {
+        self.boards.get(board).map_or(&[], |records| records.as_slice())
+    }
+
+    /// Boards that have records.
+    #[inline]
+    pub fn boards(&self) -> impl Iterator<Item=&String> {
+        self.boards.keys()
+    }
+
+    /// Place a time would get on the board, if it's fast enough to get one.
+    /// Equal times keep the older record first.
+    pub fn rank(&self, board: &str, time_ms: u64) -> Option<usize> {
+        let rank = self.records(board)
+            .iter()
+            .filter(|record| record.time_ms <= time_ms)
+            .count();
+        if rank < RECORDS { Some(rank) } else { None }
+    }
+
+    /// Add the record to its board, returns its place.
+    pub fn insert(&mut self, board: &str, record: Record) -> Option<usize> {
+        let rank = self.rank(board, record.time_ms)?;
+        let records = self.boards.entry(board.to_string()).or_default();
+        records.insert(rank, record);
+        records.truncate(RECORDS);
+        Some(rank)
+    }
+}
+
+/// Name of the leaderboard of a difficulty, each custom size and amount of
+/// mines gets its own.
+pub(crate) fn board_name(difficulty: &Difficulty) -> String {
+    match difficulty {
+        Difficulty::Beginner => "beginner".to_string(),
+        Difficulty::Intermediate => "intermediate".to_string(),
+        Difficulty::Expert => "expert".to_string(),
+        Difficulty::Custom { size, mines } => {
+            format!("custom {}x{}:{}", size.columns(), size.rows(), mines)
+        }
+    }
+}
+
+/// Resource
+///
+/// A won game waiting for the player's name to become a record.
+#[derive(Default)]
+pub(crate) struct NewRecord {
+    board: String,
+    record: Option<Record>,
+    name: String,
+}
+
+impl NewRecord {
+    fn text(&self, font: Handle<Font>, colors: &ui::Colors) -> Text {
+        let time = self.record.as_ref().map_or(0, |record| record.time_ms);
+
+        let mut lines = ui::OverlayLines::new(font);
+        lines
+            .line("new record!", colors.text)
+            .line(format!("{} in {}", self.board, format_time(time)), colors.text)
+            .empty()
+            .line(format!("name: {}_", self.name), colors.text)
+            .empty()
+            .line("enter  save", colors.dark);
+        lines.build()
+    }
+}
+
+/// Resource
+///
+/// Board that the high score view shows.
+#[derive(Default)]
+pub(crate) struct HighScoresView {
+    board: String,
+    // the record that was just set
+    highlight: Option<usize>,
+}
+
+impl HighScoresView {
+    fn text(&self, leaderboard: &Leaderboard, font: Handle<Font>, colors: &ui::Colors, bindings: &Bindings) -> Text {
+        let mut lines = ui::OverlayLines::new(font);
+        lines.line(format!("high scores {}", self.board), colors.text).empty();
+
+        let records = leaderboard.records(&self.board);
+        if records.is_empty() {
+            lines.line("no records yet", colors.dark);
+        }
+        for (rank, record) in records.iter().enumerate() {
+            let name = record.name.chars().take(NAME_WIDTH).collect::<String>();
+            let color = if self.highlight == Some(rank) { colors.highlight } else { colors.text };
+            lines.line(format!(
+                "{:>2} {:<width$} {:>8} {:>3}",
+                rank + 1,
+                name,
+                format_time(record.time_ms),
+                record.bbbv,
+                width = NAME_WIDTH,
+            ), color);
+        }
+
+        lines
+            .empty()
+            .line("left/right  board", colors.dark)
+            .line(format!("{:<12}close", format!("{:?}", bindings.high_scores)), colors.dark);
+        lines.build()
+    }
+
+    // every preset, the boards with records and the current one
+    fn boards(&self, leaderboard: &Leaderboard) -> Vec<String> {
+        let mut boards = [Difficulty::Beginner, Difficulty::Intermediate, Difficulty::Expert]
+            .iter()
+            .map(board_name)
+            .collect::<Vec<_>>();
+        for board in leaderboard.boards().chain(std::iter::once(&self.board)) {
+            if !boards.contains(board) {
+                boards.push(board.clone());
+            }
+        }
+        boards
+    }
+
+    fn cycle(&mut self, leaderboard: &Leaderboard, forward: bool) {
+        let boards = self.boards(leaderboard);
+        let pos = boards.iter().position(|board| *board == self.board).unwrap_or(0);
+        let pos = (if forward { pos + 1 } else { pos + boards.len() - 1 }) % boards.len();
+        self.board = boards[pos].clone();
+        self.highlight = None;
+    }
+}
+
+#[inline]
+fn format_time(time_ms: u64) -> String {
+    format!("{}.{:03}", time_ms / 1000, time_ms % 1000)
+}
+
+// ask for a name when a won game is fast enough for the leaderboard
+pub(super) fn check_record(
+    mut won_events: EventReader<GameWon>,
+    leaderboard: Res<Leaderboard>,
+    settings: Res<Settings>,
+    mut new_record: ResMut<NewRecord>,
+    mut notice: ResMut<Notice>,
+    mut state: ResMut<State<GameState>>,
+) {
+    for won in won_events.iter() {
+        if let Some(reason) = won.unranked {
+            notice.set(format!("{} don't make the leaderboard", reason));
+            continue;
+        }
+
+        let board = board_name(&won.difficulty);
+        let time_ms = won.time.as_millis() as u64;
+        if leaderboard.rank(&board, time_ms).is_none() {
+            continue;
+        }
+
+        *new_record = NewRecord {
+            board,
+            record: Some(Record {
+                name: String::new(),
+                date: utils::today(),
+                time_ms,
+                bbbv: won.bbbv,
+                seed: won.seed,
+            }),
+            name: settings.player_name.clone(),
+        };
+        let _ = state.push(GameState::NewRecord);
+    }
+}
+
+pub(super) fn spawn_new_record(
+    mut cmd: Commands,
+    asset_server: Res<AssetServer>,
+    colors: Res<ui::Colors>,
+    new_record: Res<NewRecord>,
+) {
+    let text = new_record.text(asset_server.get_handle(ui::FONT), &colors);
+    ui::Overlay::spawn(&mut cmd, colors.overlay, text);
+}
+
+// type the name, enter adds the record and shows the board it's on
+#[allow(clippy::too_many_arguments)]
+pub(super) fn handle_name_input(
+    key: Res<Input<KeyCode>>,
+    mut chars: EventReader<ReceivedCharacter>,
+    mut new_record: ResMut<NewRecord>,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut settings: ResMut<Settings>,
+    mut view: ResMut<HighScoresView>,
+    mut notice: ResMut<Notice>,
+    mut state: ResMut<State<GameState>>,
+) {
+    for ch in chars.iter() {
+        let allowed = ch.char.is_alphanumeric() || ch.char == ' ' || ch.char == '-' || ch.char == '_';
+        if allowed && new_record.name.chars().count() < NAME_LENGTH {
+            new_record.name.push(ch.char);
+        }
+    }
+    if key.just_pressed(KeyCode::Back) {
+        new_record.name.pop();
+    }
+    // on release, so the high score view doesn't see the key press
+    if !key.just_released(KeyCode::Return) {
+        return;
+    }
+
+    let name = match new_record.name.trim() {
+        "" => "anonymous".to_string(),
+        name => name.to_string(),
+    };
+    let board = new_record.board.clone();
+    let mut record = match new_record.record.take() {
+        Some(record) => record,
+        None => { return; }
+    };
+    record.name = name.clone();
+
+    let rank = leaderboard.insert(&board, record);
+    if let Err(err) = leaderboard.save() {
+        warn!("leaderboard not saved: {}", err);
+        notice.set("leaderboard not saved");
+    }
+
+    if settings.player_name != name {
+        settings.player_name = name;
+        if let Err(err) = settings.save() {
+            warn!("settings not saved: {}", err);
+            notice.set("settings not saved");
+        }
+    }
+
+    *view = HighScoresView {
+        board,
+        highlight: rank,
+    };
+    let _ = state.set(GameState::HighScores);
+}
+
+pub(super) fn update_new_record(
+    asset_server: Res<AssetServer>,
+    colors: Res<ui::Colors>,
+    new_record: Res<NewRecord>,
+    mut text_query: Query<&mut Text, With<ui::OverlayText>>,
+) {
+    if !new_record.is_changed() {
+        return;
+    }
+
+    for mut text in text_query.iter_mut() {
+        *text = new_record.text(asset_server.get_handle(ui::FONT), &colors);
+    }
+}
+
+// show the high scores of the current difficulty on H key press
+pub(super) fn open_high_scores(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    difficulty: Res<Difficulty>,
+    mut view: ResMut<HighScoresView>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !key.just_released(bindings.high_scores) {
+        return;
+    }
+    match state.current() {
+        GameState::Run | GameState::Over | GameState::Won => {}
+        _ => { return; }
+    }
+
+    *view = HighScoresView {
+        board: board_name(&difficulty),
+        highlight: None,
+    };
+    let _ = state.push(GameState::HighScores);
+}
+
+pub(super) fn spawn_high_scores(
+    mut cmd: Commands,
+    asset_server: Res<AssetServer>,
+    colors: Res<ui::Colors>,
+    bindings: Res<Bindings>,
+    leaderboard: Res<Leaderboard>,
+    view: Res<HighScoresView>,
+) {
+    let text = view.text(&leaderboard, asset_server.get_handle(ui::FONT), &colors, &bindings);
+    ui::Overlay::spawn(&mut cmd, colors.overlay, text);
+}
+
+// left and right go through the boards, the same key or enter closes it
+pub(super) fn handle_high_scores_input(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    leaderboard: Res<Leaderboard>,
+    mut view: ResMut<HighScoresView>,
+    mut state: ResMut<State<GameState>>,
+) {
+    // opened on release, so the same key press can't close it right away
+    if key.just_pressed(bindings.high_scores) || key.just_pressed(KeyCode::Return) {
+        let _ = state.pop();
+        return;
+    }
+
+    if key.just_pressed(KeyCode::Right) {
+        view.cycle(&leaderboard, true);
+    } else if key.just_pressed(KeyCode::Left) {
+        view.cycle(&leaderboard, false);
+    }
+}
+
+pub(super) fn update_high_scores(
+    asset_server: Res<AssetServer>,
+    colors: Res<ui::Colors>,
+    bindings: Res<Bindings>,
+    leaderboard: Res<Leaderboard>,
+    view: Res<HighScoresView>,
+    mut text_query: Query<&mut Text, With<ui::OverlayText>>,
+) {
+    if !view.is_changed() {
+        return;
+    }
+
+    for mut text in text_query.iter_mut() {
+        *text = view.text(&leaderboard, asset_server.get_handle(ui::FONT), &colors, &bindings);
+    }
+}
@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// Resource
+///
+/// Clicks on the grid during the current game. Wasted clicks are the ones
+/// that change nothing on the board.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Clicks {
+    pub left: u32,
+    pub right: u32,
+    pub chord: u32,
+    pub wasted: u32,
+}
+
+impl Clicks {
+    #[inline(always)]
+    pub fn total(&self) -> u32 { self.left + self.right + self.chord }
+
+    #[inline(always)]
+    pub fn effective(&self) -> u32 { self.total().saturating_sub(self.wasted) }
+}
+
+/// How efficiently a game is played, measured against the board's 3BV.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Metrics {
+    pub bbbv: usize,
+    // 3BV that is revealed, all of it for a won game
+    pub solved_bbbv: usize,
+    pub time: Duration,
+    pub clicks: Clicks,
+}
+
+impl Metrics {
+    pub fn new(board: &board::Board, time: Duration, clicks: Clicks) -> Self {
+        Self {
+            bbbv: board.bbbv(),
+            solved_bbbv: board.solved_bbbv(),
+            time,
+            clicks,
+        }
+    }
+
+    #[inline]
+    pub fn bbbv_per_second(&self) -> f32 {
+        let seconds = self.time.as_secs_f32();
+        if seconds <= 0. {
+            return 0.;
+        }
+        self.solved_bbbv as f32 / seconds
+    }
+
+    /// Index of efficiency: solved 3BV per click.
+    #[inline]
+    pub fn ioe(&self) -> f32 {
+        if self.clicks.total() == 0 {
+            return 0.;
+        }
+        self.solved_bbbv as f32 / self.clicks.total() as f32
+    }
+
+    /// Time divided by 3BV/s, lower is better.
+    #[inline]
+    pub fn rqp(&self) -> f32 {
+        let bbbv_per_second = self.bbbv_per_second();
+        if bbbv_per_second <= 0. {
+            return 0.;
+        }
+        self.time.as_secs_f32() / bbbv_per_second
+    }
+
+    /// Share of the clicks that changed the board.
+    #[inline]
+    pub fn correctness(&self) -> f32 {
+        if self.clicks.total() == 0 {
+            return 0.;
+        }
+        self.clicks.effective() as f32 / self.clicks.total() as f32
+    }
+
+    fn text(&self, hints: u32, font: Handle<Font>, colors: &ui::Colors) -> Text {
+        let clicks = self.clicks;
+        let mut lines = ui::OverlayLines::new(font);
+        lines
+            .line(format!("3BV {}/{}  3BV/s {:.2}", self.solved_bbbv, self.bbbv, self.bbbv_per_second()), colors.text)
+            .line(format!("IOE {:.2}  RQP {:.1}", self.ioe(), self.rqp()), colors.text)
+            .line(format!("correctness {:.1}%", self.correctness() * 100.), colors.text)
+            .line(format!(
+                "clicks {} L{} R{} C{} W{}",
+                clicks.total(),
+                clicks.left,
+                clicks.right,
+                clicks.chord,
+                clicks.wasted,
+            ), colors.dark);
+        if hints > 0 {
+            lines.line(format!("hints {}", hints), colors.dark);
+        }
+        lines.build()
+    }
+}
+
+pub(super) fn reset_clicks(mut clicks: ResMut<Clicks>) {
+    *clicks = Clicks::default();
+}
+
+// show how the game went once it's over
+pub(super) fn spawn_summary(
+    mut cmd: Commands,
+    asset_server: Res<AssetServer>,
+    colors: Res<ui::Colors>,
+    board: Res<board::Board>,
+    game_time: Res<GameTime>,
+    clicks: Res<Clicks>,
+    hints: Res<hint::Hints>,
+) {
+    let metrics = Metrics::new(&board, game_time.elapsed(), *clicks);
+    let text = metrics.text(hints.used(), asset_server.get_handle(ui::FONT), &colors);
+    ui::Panel::spawn(&mut cmd, colors.overlay, text);
+}
+
+// the summary panel and its text
+type PanelFilter = Or<(With<ui::Panel>, With<ui::PanelText>)>;
+
+// hide or show the summary, so the whole board can be looked at
+pub(super) fn toggle_summary(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    state: Res<State<GameState>>,
+    // ui visibility isn't inherited, the text is hidden along with its panel
+    mut panel_query: Query<&mut Visibility, PanelFilter>,
+) {
+    if !key.just_released(bindings.summary) || state.current().is_dialog() {
+        return;
+    }
+
+    for mut visibility in panel_query.iter_mut() {
+        visibility.is_visible = !visibility.is_visible;
+    }
+}
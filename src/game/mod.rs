@@ -1,12 +1,30 @@
+use std::fmt;
+use std::time::Duration;
+
+use bevy::core::Stopwatch;
+use bevy::log::info;
+use bevy::prelude::KeyCode;
+use serde::{Deserialize, Serialize};
+
 pub use game::*;
 
+pub mod board;
 pub mod grid;
+pub mod custom;
+mod exchange;
 mod game;
+mod hint;
+mod leaderboard;
+mod metrics;
+mod probability;
+pub mod replay;
+mod resume;
+mod stats;
 pub mod ui;
 
 /// Resource
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub(crate) enum Difficulty {
     Beginner,
     Intermediate,
@@ -40,13 +58,337 @@ impl Difficulty {
         };
     }
 
-    #[inline(always)]
-    pub fn change(&mut self, set: Self) {
-        println!("change difficulty {:?}", set);
+    /// Check if the amount of mines still allows the first click guarantee
+    /// to hold.
+    pub fn validate(&self, first_click: board::FirstClick) -> Result<(), DifficultyError> {
+        if self.mines() == 0 {
+            return Err(DifficultyError::NoMines);
+        }
+
+        let max = first_click.max_mines(self.size());
+        if self.mines() > max {
+            return Err(DifficultyError::TooManyMines {
+                mines: self.mines(),
+                max,
+                first_click,
+            });
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn change(&mut self, set: Self, first_click: board::FirstClick) -> Result<(), DifficultyError> {
+        set.validate(first_click)?;
+        info!("change difficulty {:?}", set);
         *self = set;
+        Ok(())
     }
 }
 
 impl Default for Difficulty {
     fn default() -> Self { Difficulty::Beginner }
 }
+
+/// Resource
+///
+/// Seed the current game's mines are placed with. The same seed, difficulty
+/// and first click always result in the same board.
+pub(crate) struct Seed {
+    // none when the mines come from a layout
+    current: Option<u64>,
+    // seed for the next game, instead of a random one
+    next: Option<u64>,
+}
+
+impl Seed {
+    #[inline(always)]
+    pub fn current(&self) -> Option<u64> { self.current }
+
+    /// Use this seed for the next game.
+    #[inline(always)]
+    pub fn set_next(&mut self, seed: u64) { self.next = Some(seed); }
+
+    /// Pick the seed for a new game.
+    #[inline]
+    pub fn advance(&mut self) {
+        self.current = Some(self.next.take().unwrap_or_else(rand::random));
+    }
+
+    /// The current game's mines weren't placed with a seed.
+    #[inline(always)]
+    pub fn clear(&mut self) { self.current = None; }
+
+    /// Seed of a game that is resumed.
+    #[inline(always)]
+    pub fn restore(&mut self, current: Option<u64>) { self.current = current; }
+}
+
+impl Default for Seed {
+    fn default() -> Self {
+        Self {
+            current: Some(rand::random()),
+            next: None,
+        }
+    }
+}
+
+/// Resource
+///
+/// Mines of the next game, instead of placing them on the first reveal.
+#[derive(Default)]
+pub(crate) struct NextLayout(pub Option<board::Layout>);
+
+/// Resource
+///
+/// The player picked the difficulty, so the settings keep it. Boards that are
+/// loaded change the difficulty too, but don't count.
+#[derive(Default)]
+pub(crate) struct DifficultyChosen(pub bool);
+
+/// Resource
+///
+/// Board of the next game with cells already revealed or flagged, from a
+/// position that is loaded.
+#[derive(Default)]
+pub(crate) struct NextPosition(pub Option<board::Board>);
+
+/// Resource
+///
+/// Why the running game doesn't count for records, if it doesn't.
+#[derive(Default)]
+pub(crate) struct Unranked(pub Option<&'static str>);
+
+/// Resource
+///
+/// Places the mines of new games.
+pub(crate) struct Generator(pub Box<dyn board::MineGenerator>);
+
+impl Generator {
+    /// Switch to the next generator that ships with the game.
+    pub fn next(&mut self) {
+        let generators = board::generators();
+        let pos = generators
+            .iter()
+            .position(|g| g.name() == self.0.name())
+            .map_or(0, |pos| (pos + 1) % generators.len());
+
+        self.0 = generators.into_iter().nth(pos).unwrap();
+    }
+
+    /// The first click guarantee the difficulty has to fit with this
+    /// generator.
+    #[inline]
+    pub fn first_click(&self, chosen: board::FirstClick) -> board::FirstClick {
+        self.0.first_click(chosen)
+    }
+}
+
+impl Default for Generator {
+    fn default() -> Self { Self(Box::new(board::UniformGenerator)) }
+}
+
+/// Resource
+///
+/// Difficulties that only get boards which can be solved from the first
+/// click without guessing, whatever the active generator is.
+#[derive(Default)]
+pub(crate) struct NoGuess {
+    beginner: bool,
+    intermediate: bool,
+    expert: bool,
+    custom: bool,
+    generator: board::NoGuessGenerator,
+}
+
+impl NoGuess {
+    #[inline]
+    pub fn enabled(&self, difficulty: &Difficulty) -> bool {
+        *self.get(difficulty)
+    }
+
+    #[inline]
+    pub fn toggle(&mut self, difficulty: &Difficulty) -> bool {
+        let enabled = self.get_mut(difficulty);
+        *enabled = !*enabled;
+        *enabled
+    }
+
+    /// Places the mines of difficulties that have no-guess boards enabled.
+    #[inline(always)]
+    pub fn generator(&self) -> &dyn board::MineGenerator { &self.generator }
+
+    #[inline]
+    fn get(&self, difficulty: &Difficulty) -> &bool {
+        use Difficulty::*;
+        match difficulty {
+            Beginner => &self.beginner,
+            Intermediate => &self.intermediate,
+            Expert => &self.expert,
+            Custom { .. } => &self.custom,
+        }
+    }
+
+    #[inline]
+    fn get_mut(&mut self, difficulty: &Difficulty) -> &mut bool {
+        use Difficulty::*;
+        match difficulty {
+            Beginner => &mut self.beginner,
+            Intermediate => &mut self.intermediate,
+            Expert => &mut self.expert,
+            Custom { .. } => &mut self.custom,
+        }
+    }
+}
+
+/// Resource
+///
+/// Whether right click cycles through a question mark after the flag.
+pub(crate) struct QuestionMarks(pub bool);
+
+impl Default for QuestionMarks {
+    fn default() -> Self { Self(true) }
+}
+
+/// Resource
+///
+/// Short message for the player, shown in the window title next to the seed
+/// until the next game starts.
+#[derive(Default)]
+pub(crate) struct Notice(Option<String>);
+
+impl Notice {
+    #[inline(always)]
+    pub fn get(&self) -> Option<&str> { self.0.as_deref() }
+
+    #[inline]
+    pub fn set(&mut self, text: impl Into<String>) { self.0 = Some(text.into()); }
+
+    #[inline(always)]
+    pub fn clear(&mut self) { self.0 = None; }
+}
+
+/// Resource
+///
+/// Keys for the game's actions, part of the settings.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Bindings {
+    pub beginner: KeyCode,
+    pub intermediate: KeyCode,
+    pub expert: KeyCode,
+    pub custom: KeyCode,
+    pub first_click: KeyCode,
+    pub question_marks: KeyCode,
+    pub replay_seed: KeyCode,
+    pub no_guess: KeyCode,
+    pub generator: KeyCode,
+    pub enter_seed: KeyCode,
+    pub high_scores: KeyCode,
+    pub stats: KeyCode,
+    pub summary: KeyCode,
+    pub play_replay: KeyCode,
+    pub export_board: KeyCode,
+    pub share_position: KeyCode,
+    pub hint: KeyCode,
+    pub probabilities: KeyCode,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            beginner: KeyCode::F1,
+            intermediate: KeyCode::F2,
+            expert: KeyCode::F3,
+            custom: KeyCode::F4,
+            first_click: KeyCode::F5,
+            question_marks: KeyCode::F6,
+            replay_seed: KeyCode::F7,
+            no_guess: KeyCode::F8,
+            generator: KeyCode::F9,
+            enter_seed: KeyCode::S,
+            high_scores: KeyCode::H,
+            stats: KeyCode::T,
+            summary: KeyCode::M,
+            play_replay: KeyCode::P,
+            export_board: KeyCode::B,
+            share_position: KeyCode::C,
+            hint: KeyCode::G,
+            probabilities: KeyCode::O,
+        }
+    }
+}
+
+/// Resource
+///
+/// Time spent on the current game, starts on the first reveal.
+#[derive(Default)]
+pub(crate) struct GameTime(Stopwatch);
+
+impl GameTime {
+    #[inline(always)]
+    pub fn elapsed(&self) -> Duration { self.0.elapsed() }
+
+    #[inline(always)]
+    pub fn tick(&mut self, delta: Duration) { self.0.tick(delta); }
+
+    #[inline(always)]
+    pub fn reset(&mut self) { self.0.reset(); }
+
+    #[inline(always)]
+    pub fn set(&mut self, elapsed: Duration) { self.0.set_elapsed(elapsed); }
+}
+
+/// Event
+///
+/// Sent when every cell without a mine is revealed.
+pub(crate) struct GameWon {
+    pub time: Duration,
+    pub difficulty: Difficulty,
+    pub bbbv: usize,
+    pub seed: Option<u64>,
+    // games that don't make records, and why
+    pub unranked: Option<&'static str>,
+}
+
+#[derive(Debug)]
+pub(crate) enum DifficultyError {
+    TooManyMines {
+        mines: usize,
+        max: usize,
+        first_click: board::FirstClick,
+    },
+    NoMines,
+    ColumnsOutOfRange {
+        min: usize,
+        max: usize,
+    },
+    RowsOutOfRange {
+        min: usize,
+        max: usize,
+    },
+}
+
+impl fmt::Display for DifficultyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DifficultyError::TooManyMines { mines, max, first_click } => {
+                write!(f, "{} mines is too many, {:?} first click allows at most {}", mines, first_click, max)
+            }
+            DifficultyError::NoMines => {
+                write!(f, "at least 1 mine is needed")
+            }
+            DifficultyError::ColumnsOutOfRange { min, max: usize::MAX } => {
+                write!(f, "at least {} columns are needed", min)
+            }
+            DifficultyError::ColumnsOutOfRange { min, max } => {
+                write!(f, "columns go from {} to {}", min, max)
+            }
+            DifficultyError::RowsOutOfRange { min, max: usize::MAX } => {
+                write!(f, "at least {} rows are needed", min)
+            }
+            DifficultyError::RowsOutOfRange { min, max } => {
+                write!(f, "rows go from {} to {}", min, max)
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy::tasks::AsyncComputeTaskPool;
+
+use super::*;
+
+// a solver and what it found for a board
+type Computed = (board::MineProbabilities, Option<Vec<Option<f32>>>);
+
+/// Resource
+///
+/// Tints every covered cell by its chance to be a mine, for analysis and
+/// practice. Chances are computed off the main thread, large boards may
+/// take longer than a frame.
+#[derive(Default)]
+pub(crate) struct ProbabilityOverlay {
+    enabled: bool,
+    solver: board::MineProbabilities,
+    // the solver is away computing
+    busy: bool,
+    computed: Arc<Mutex<Option<Computed>>>,
+    // the board changed since the last computation started
+    stale: bool,
+}
+
+/// Tint on top of the tile of a covered cell, a child of the tile.
+#[derive(Component)]
+pub(crate) struct ProbabilityTint(usize);
+
+// show or hide the mine probabilities on O key press, games played with them
+// shown are unranked
+pub(super) fn toggle_probabilities(
+    mut cmd: Commands,
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    state: Res<State<GameState>>,
+    mut overlay: ResMut<ProbabilityOverlay>,
+    mut notice: ResMut<Notice>,
+    tint_query: Query<Entity, With<ProbabilityTint>>,
+) {
+    if !key.just_released(bindings.probabilities) || state.current().is_dialog() {
+        return;
+    }
+
+    overlay.enabled = !overlay.enabled;
+    overlay.stale = overlay.enabled;
+    if !overlay.enabled {
+        for entity in tint_query.iter() {
+            cmd.entity(entity).despawn_recursive();
+        }
+    }
+    if overlay.enabled {
+        notice.set("mine probabilities on, games played with them don't make the leaderboard");
+    } else {
+        notice.set("mine probabilities off");
+    }
+}
+
+// start computing when the board changes, and tint the tiles with what
+// comes back as long as the board is still the same
+#[allow(clippy::too_many_arguments)]
+pub(super) fn update_probabilities(
+    mut cmd: Commands,
+    pool: Res<AsyncComputeTaskPool>,
+    board: Res<board::Board>,
+    colors: Res<ui::Colors>,
+    tile_size: Res<grid::TileSize>,
+    state: Res<State<GameState>>,
+    mut overlay: ResMut<ProbabilityOverlay>,
+    mut unranked: ResMut<Unranked>,
+    grid_query: Query<&grid::Grid>,
+    tint_query: Query<(Entity, &ProbabilityTint)>,
+) {
+    if !overlay.enabled {
+        return;
+    }
+    if *state.current() == GameState::Run && unranked.0.is_none() {
+        unranked.0 = Some("games with mine probabilities");
+    }
+
+    let over = board.is_lost() || board.is_cleared();
+    if board.is_changed() {
+        overlay.stale = true;
+        // revealed cells lose their tint right away, the rest keep theirs
+        // until the new chances are in
+        for (entity, tint) in tint_query.iter() {
+            let index = tint.0;
+            if over || board.revealed() == 0 || index >= board.size().capacity() || board.cell_at(index).is_revealed() {
+                cmd.entity(entity).despawn_recursive();
+            }
+        }
+    }
+
+    let computed = overlay.computed.lock().unwrap().take();
+    if let Some((solver, chances)) = computed {
+        overlay.solver = solver;
+        overlay.busy = false;
+        if let (false, Some(chances), Ok(grid)) = (overlay.stale, chances, grid_query.get_single()) {
+            for (entity, _) in tint_query.iter() {
+                cmd.entity(entity).despawn_recursive();
+            }
+            spawn_tints(&mut cmd, grid, &colors, &tile_size, &chances);
+        }
+    }
+
+    // a busy solver starts again once it's back
+    if !overlay.stale || overlay.busy || over {
+        return;
+    }
+    let mut solver = std::mem::take(&mut overlay.solver);
+    overlay.busy = true;
+    overlay.stale = false;
+
+    let size = board.size();
+    let mines = board.mines();
+    let numbers = board::visible_numbers(&board);
+    let computed = overlay.computed.clone();
+    pool.spawn(async move {
+        let chances = solver.compute(size, mines, &numbers);
+        *computed.lock().unwrap() = Some((solver, chances));
+    }).detach();
+}
+
+fn spawn_tints(
+    cmd: &mut Commands,
+    grid: &grid::Grid,
+    colors: &ui::Colors,
+    tile_size: &grid::TileSize,
+    chances: &[Option<f32>],
+) {
+    let tile_size: Vec2 = tile_size.into();
+    for (index, chance) in chances.iter().enumerate() {
+        let chance = match chance {
+            Some(chance) => *chance,
+            None => { continue; }
+        };
+        let (col, row) = grid.size().position_of(index);
+        let tile_entity = match grid.get_tile(col, row) {
+            Some(tile_entity) => tile_entity,
+            None => { continue; }
+        };
+
+        // certainly safe cells stand out from merely unlikely ones
+        let (mut color, alpha) = if chance <= 0. {
+            (colors.highlight, 0.35)
+        } else {
+            (colors.error, 0.1 + 0.6 * chance)
+        };
+        color.set_a(alpha);
+        let tint = cmd.spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(tile_size),
+                anchor: Anchor::BottomLeft,
+                ..default()
+            },
+            transform: Transform::from_xyz(0., 0., 0.5),
+            ..default()
+        })
+            .insert(ProbabilityTint(index))
+            .id();
+        cmd.entity(tile_entity).add_child(tint);
+    }
+}
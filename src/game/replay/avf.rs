@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use crate::game::board;
+use crate::game::grid::GridSize;
+
+use super::*;
+use super::import::{replay_from_mouse, MouseEvent, MouseKind, Reader};
+
+// size of an event
+const EVENT: usize = 8;
+
+/// Read a Minesweeper Arbiter (AVF) replay. After the version and the level,
+/// with the size and mines for custom boards, come a row and column byte
+/// per mine counting from one, a text header in brackets and then the
+/// events, until one without any time. The text after the events starts
+/// with the player's name.
+pub(crate) fn read_avf(bytes: &[u8]) -> Result<(Replay, ReplayInfo), ReplayError> {
+    let mut reader = Reader::new(bytes);
+    let version = reader.u8()?;
+    reader.skip(4)?;
+
+    let (columns, rows, count) = match reader.u8()? {
+        3 => (8, 8, 10),
+        4 => (16, 16, 40),
+        5 => (30, 16, 99),
+        6 => {
+            let columns = reader.u8()? as usize + 1;
+            let rows = reader.u8()? as usize + 1;
+            (columns, rows, reader.u16()? as usize)
+        }
+        level => {
+            return Err(ReplayError::Unsupported(format!("AVF level {}", level)));
+        }
+    };
+    let size = GridSize::new(columns, rows);
+
+    let mut mines = Vec::with_capacity(count);
+    for _ in 0..count {
+        let row = reader.u8()? as usize;
+        let col = reader.u8()? as usize;
+        if col == 0 || row == 0 || col > columns || row > rows {
+            return Err(ReplayError::Layout(board::LayoutError::OutOfBounds((col, row))));
+        }
+        mines.push((col - 1, row - 1));
+    }
+
+    // the header in brackets holds the clone's settings and dates
+    while reader.u8()? != b'[' {}
+    while reader.u8()? != b']' {}
+
+    // events start with a second of time and a high byte of at most one
+    let mut event = [0u8; EVENT];
+    while event[2] != 1 || event[1] > 1 {
+        event[0] = event[1];
+        event[1] = event[2];
+        event[2] = reader.u8()?;
+    }
+    event[3..].copy_from_slice(reader.take(EVENT - 3)?);
+
+    let mut mouse = Vec::new();
+    while event[2] != 0 || event[6] != 0 {
+        // whole seconds start at one, hundredths come on their own
+        let seconds = (event[6] as u64 * 256 + event[2] as u64).saturating_sub(1);
+        let time_ms = seconds * 1000 + event[4] as u64 * 10;
+        let x = event[1] as u16 * 256 + event[3] as u16;
+        let y = event[5] as u16 * 256 + event[7] as u16;
+        let kind = match event[0] {
+            1 => Some(MouseKind::Move),
+            3 => Some(MouseKind::LeftDown),
+            5 | 21 => Some(MouseKind::LeftUp),
+            9 => Some(MouseKind::RightDown),
+            17 | 145 => Some(MouseKind::RightUp),
+            33 => Some(MouseKind::MiddleDown),
+            65 | 193 => Some(MouseKind::MiddleUp),
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            mouse.push(MouseEvent { time_ms, x, y, kind });
+        }
+
+        event.copy_from_slice(reader.take(EVENT)?);
+    }
+
+    let footer = String::from_utf8_lossy(reader.rest()).to_string();
+    let player = footer
+        .split(['\r', '\n', '\0'])
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty())
+        .map(|line| line.to_string());
+
+    // question marks aren't part of the file
+    let replay = replay_from_mouse(size, &mines, false, &mouse);
+    let info = ReplayInfo {
+        format: "AVF",
+        version: version as u32,
+        program: Some("Minesweeper Arbiter".to_string()),
+        player,
+        time: mouse.last().map_or(Duration::ZERO, |event| Duration::from_millis(event.time_ms)),
+    };
+    Ok((replay, info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // custom 8x8 board with a mine in the top right corner, a left click on
+    // the top left cell at half a second, and the player after the events
+    fn avf() -> Vec<u8> {
+        let mut bytes = vec![2, 0, 0, 0, 0];
+        // level, columns and rows less one, mines
+        bytes.extend_from_slice(&[6, 7, 7, 0, 1]);
+        // row and column from one
+        bytes.extend_from_slice(&[1, 8]);
+        bytes.extend_from_slice(b"[0|1.1.2000|settings]");
+        // kind, x high, seconds, x low, hundredths, y high, seconds high, y low
+        bytes.extend_from_slice(&[3, 0, 1, 8, 50, 0, 0, 8]);
+        bytes.extend_from_slice(&[5, 0, 1, 8, 50, 0, 0, 8]);
+        bytes.extend_from_slice(&[0; EVENT]);
+        bytes.extend_from_slice(b"anna\r\nmore");
+        bytes
+    }
+
+    #[test]
+    fn read_valid() {
+        let (replay, info) = read_avf(&avf()).unwrap();
+        assert_eq!(info.format, "AVF");
+        assert_eq!(info.version, 2);
+        assert_eq!(info.player.as_deref(), Some("anna"));
+        assert_eq!(info.time, Duration::from_millis(500));
+
+        assert_eq!((replay.columns, replay.rows), (8, 8));
+        assert_eq!(replay.mines, vec![(7, 7)]);
+        assert!(!replay.question_marks);
+        assert_eq!(replay.events.len(), 1);
+        let event = &replay.events[0];
+        assert_eq!((event.col, event.row, event.time_ms), (0, 7, 500));
+        assert_eq!((event.button, event.action), (ReplayButton::Left, ReplayAction::Reveal));
+        assert!(replay.check().is_ok());
+    }
+
+    #[test]
+    fn read_unsupported_level() {
+        let mut bytes = avf();
+        bytes[5] = 7;
+        assert!(matches!(read_avf(&bytes), Err(ReplayError::Unsupported(_))));
+    }
+}
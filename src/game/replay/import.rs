@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use crate::game::grid::GridSize;
+
+use super::*;
+
+/// Size of a cell in pixels in the clones that write RMV and AVF files,
+/// mouse positions are in pixels from the board's top left corner.
+const CELL_PIXELS: u16 = 16;
+
+/// What a replay file says about itself.
+#[derive(Clone, Debug)]
+pub(crate) struct ReplayInfo {
+    pub format: &'static str,
+    // version of the file format
+    pub version: u32,
+    // clone that wrote the file
+    pub program: Option<String>,
+    pub player: Option<String>,
+    // time of the last event
+    pub time: Duration,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(super) enum MouseKind {
+    Move,
+    LeftDown,
+    LeftUp,
+    RightDown,
+    RightUp,
+    MiddleDown,
+    MiddleUp,
+}
+
+/// Raw mouse input of a replay file, before it's turned into actions on
+/// cells.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct MouseEvent {
+    pub time_ms: u64,
+    pub x: u16,
+    pub y: u16,
+    pub kind: MouseKind,
+}
+
+/// Reads the big endian numbers of a replay file, running out of bytes is
+/// an error.
+pub(super) struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    #[inline]
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], ReplayError> {
+        let end = self.pos.checked_add(len).filter(|&end| end <= self.bytes.len())
+            .ok_or(ReplayError::Truncated)?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    #[inline]
+    pub fn skip(&mut self, len: usize) -> Result<(), ReplayError> {
+        self.take(len).map(|_| ())
+    }
+
+    #[inline]
+    pub fn u8(&mut self) -> Result<u8, ReplayError> {
+        Ok(self.take(1)?[0])
+    }
+
+    #[inline]
+    pub fn u16(&mut self) -> Result<u16, ReplayError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    #[inline]
+    pub fn u24(&mut self) -> Result<u32, ReplayError> {
+        let bytes = self.take(3)?;
+        Ok(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]))
+    }
+
+    /// Text of the given length, anything that isn't UTF-8 is replaced.
+    #[inline]
+    pub fn text(&mut self, len: usize) -> Result<String, ReplayError> {
+        Ok(String::from_utf8_lossy(self.take(len)?).trim_end_matches('\0').to_string())
+    }
+
+    /// Everything that is left.
+    #[inline]
+    pub fn rest(&mut self) -> &'a [u8] {
+        let bytes = &self.bytes[self.pos.min(self.bytes.len())..];
+        self.pos = self.bytes.len();
+        bytes
+    }
+}
+
+/// Replay of a board with mines at column and row from the top, as the
+/// clones count them.
+pub(super) fn replay_from_mouse(
+    size: GridSize,
+    mines_from_top: &[(usize, usize)],
+    question_marks: bool,
+    mouse: &[MouseEvent],
+) -> Replay {
+    let flip = |(col, row): (usize, usize)| (col, size.rows() - 1 - row);
+    let mut replay = Replay {
+        version: Replay::VERSION,
+        columns: size.columns(),
+        rows: size.rows(),
+        mines: mines_from_top.iter().copied().map(flip).collect(),
+        seed: None,
+        question_marks,
+        revealed: Vec::new(),
+        flagged: Vec::new(),
+        events: Vec::new(),
+    };
+
+    // the same buttons do the same as in the game: left reveals on release,
+    // right flags on press, middle or left and right together chord
+    let (mut left, mut right) = (false, false);
+    let (mut chord, mut fired) = (false, false);
+    for event in mouse {
+        let cell = cell_at(size, event.x, event.y).map(flip);
+        let mut action = None;
+        match event.kind {
+            MouseKind::Move | MouseKind::MiddleDown => {}
+            MouseKind::LeftDown => {
+                left = true;
+                chord |= right;
+            }
+            MouseKind::RightDown => {
+                right = true;
+                if left {
+                    chord = true;
+                } else {
+                    action = Some((ReplayButton::Right, ReplayAction::Flag));
+                }
+            }
+            MouseKind::LeftUp | MouseKind::RightUp => {
+                if chord {
+                    if !fired {
+                        fired = true;
+                        action = Some((ReplayButton::Both, ReplayAction::Chord));
+                    }
+                } else if event.kind == MouseKind::LeftUp && left {
+                    action = Some((ReplayButton::Left, ReplayAction::Reveal));
+                }
+                if event.kind == MouseKind::LeftUp { left = false; } else { right = false; }
+                if !left && !right {
+                    chord = false;
+                    fired = false;
+                }
+            }
+            MouseKind::MiddleUp => {
+                if !chord {
+                    action = Some((ReplayButton::Middle, ReplayAction::Chord));
+                }
+            }
+        }
+
+        if let (Some((button, action)), Some((col, row))) = (action, cell) {
+            replay.events.push(ReplayEvent {
+                time_ms: event.time_ms,
+                col,
+                row,
+                button,
+                action,
+            });
+        }
+    }
+    replay
+}
+
+// column and row from the top of the cell under a mouse position
+#[inline]
+fn cell_at(size: GridSize, x: u16, y: u16) -> Option<(usize, usize)> {
+    let col = (x / CELL_PIXELS) as usize;
+    let row = (y / CELL_PIXELS) as usize;
+    if col >= size.columns() || row >= size.rows() {
+        return None;
+    }
+    Some((col, row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reader_truncated() {
+        let mut reader = Reader::new(&[1, 2, 3, 4]);
+        assert_eq!(reader.u16().unwrap(), 0x0102);
+        assert!(matches!(reader.u24(), Err(ReplayError::Truncated)));
+        assert!(matches!(reader.skip(usize::MAX), Err(ReplayError::Truncated)));
+        assert_eq!(reader.u8().unwrap(), 3);
+        assert_eq!(reader.rest(), &[4]);
+        assert!(matches!(reader.u8(), Err(ReplayError::Truncated)));
+    }
+}
@@ -0,0 +1,13 @@
+pub(crate) use avf::*;
+pub(crate) use import::*;
+pub(crate) use playback::*;
+pub(crate) use record::*;
+pub(crate) use replay::*;
+pub(crate) use rmv::*;
+
+mod avf;
+mod import;
+mod playback;
+mod record;
+mod replay;
+mod rmv;
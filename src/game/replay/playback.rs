@@ -0,0 +1,267 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::game::{board, grid, ui};
+use crate::game::{set_window_title, Bindings, GameState, GameTime, Notice};
+
+use super::*;
+
+const SPEEDS: [f32; 6] = [0.25, 0.5, 1., 2., 4., 8.];
+// index of the normal speed
+const NORMAL_SPEED: usize = 2;
+// how far left and right seek
+const SEEK: Duration = Duration::from_secs(5);
+
+/// Resource
+///
+/// The replay that is played back and how far along it is.
+pub(crate) struct Playback {
+    replay: Option<Replay>,
+    // board the first event is played on
+    start: Option<board::Board>,
+    // loaded and waiting for the board to be set up
+    pending: bool,
+    position: Duration,
+    speed: usize,
+    paused: bool,
+    // events that are applied to the board
+    applied: usize,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            replay: None,
+            start: None,
+            pending: false,
+            position: Duration::ZERO,
+            speed: NORMAL_SPEED,
+            paused: false,
+            applied: 0,
+        }
+    }
+}
+
+impl Playback {
+    /// Play the replay with the next game.
+    pub fn start(&mut self, replay: Replay) -> Result<(), ReplayError> {
+        let layout = replay.check()?;
+        let start = replay.start(&layout);
+        *self = Self {
+            replay: Some(replay),
+            start: Some(start),
+            pending: true,
+            ..default()
+        };
+        Ok(())
+    }
+
+    /// Whether the next game is a replay.
+    #[inline(always)]
+    pub fn is_pending(&self) -> bool { self.pending }
+
+    /// Board the next game starts from, when it's a replay.
+    #[inline]
+    pub fn pending_board(&self) -> Option<&board::Board> {
+        self.start.as_ref().filter(|_| self.pending)
+    }
+
+    #[inline]
+    fn duration(&self) -> Duration {
+        self.replay.as_ref().map_or(Duration::ZERO, |replay| replay.duration())
+    }
+
+    #[inline]
+    fn is_finished(&self) -> bool {
+        self.position >= self.duration()
+    }
+
+    /// Bring the board to the given time. Going back starts over from the
+    /// starting board, as the board can't undo anything.
+    fn seek(&mut self, board: &mut ResMut<board::Board>, to: Duration) {
+        let (replay, start) = match (&self.replay, &self.start) {
+            (Some(replay), Some(start)) => (replay, start),
+            _ => { return; }
+        };
+
+        let to = to.min(replay.duration());
+        if to < self.position {
+            **board = start.clone();
+            self.applied = 0;
+        }
+        while let Some(event) = replay.events.get(self.applied) {
+            if event.time() > to {
+                break;
+            }
+            replay.apply(board, event);
+            self.applied += 1;
+        }
+        self.position = to;
+    }
+
+    // cell the cursor is on, moving from the last event to the next one
+    fn cursor(&self) -> Option<Vec2> {
+        let events = &self.replay.as_ref()?.events;
+        let next = events.get(self.applied).or_else(|| events.last())?;
+        let last = match self.applied {
+            0 => next,
+            applied => &events[applied - 1],
+        };
+
+        let from = Vec2::new(last.col as f32, last.row as f32);
+        let to = Vec2::new(next.col as f32, next.row as f32);
+        let span = next.time().saturating_sub(last.time()).as_secs_f32();
+        if span <= 0. {
+            return Some(to);
+        }
+        let t = (self.position.saturating_sub(last.time()).as_secs_f32() / span).clamp(0., 1.);
+        Some(from.lerp(to, t))
+    }
+
+    fn title(&self) -> String {
+        format!(
+            "replay {:.1}/{:.1} {}x{}",
+            self.position.as_secs_f32(),
+            self.duration().as_secs_f32(),
+            SPEEDS[self.speed],
+            if self.paused { " paused" } else if self.is_finished() { " end" } else { "" },
+        )
+    }
+}
+
+/// Marks where the replay's mouse is.
+#[derive(Component)]
+pub(crate) struct PlaybackCursor;
+
+// play back the replay of the last game on P key press
+pub(crate) fn open_last_replay(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    recording: Res<Recording>,
+    mut playback: ResMut<Playback>,
+    mut notice: ResMut<Notice>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !key.just_released(bindings.play_replay) || state.current().is_dialog() {
+        return;
+    }
+
+    let replay = match recording.last() {
+        Some(replay) => replay.clone(),
+        None => {
+            notice.set("no replay yet, finish a game first");
+            return;
+        }
+    };
+    match playback.start(replay) {
+        Ok(()) => { let _ = state.set(GameState::Start); }
+        Err(err) => notice.set(format!("can't play replay: {}", err)),
+    }
+}
+
+pub(crate) fn spawn_playback(
+    mut cmd: Commands,
+    colors: Res<ui::Colors>,
+    tile_size: Res<grid::TileSize>,
+    mut playback: ResMut<Playback>,
+    mut notice: ResMut<Notice>,
+) {
+    playback.pending = false;
+
+    let tile_size: Vec2 = (&*tile_size).into();
+    cmd.spawn_bundle(SpriteBundle {
+        sprite: Sprite {
+            color: colors.highlight,
+            custom_size: Some(tile_size * 0.4),
+            ..default()
+        },
+        visibility: Visibility { is_visible: false },
+        ..default()
+    })
+        .insert(PlaybackCursor);
+
+    notice.set("space pause, left/right seek, home/end jump, up/down speed");
+}
+
+// the board stays read-only, it only follows the replay's events
+pub(crate) fn play(
+    time: Res<Time>,
+    key: Res<Input<KeyCode>>,
+    mut playback: ResMut<Playback>,
+    mut board: ResMut<board::Board>,
+    mut game_time: ResMut<GameTime>,
+    notice: Res<Notice>,
+    mut windows: ResMut<Windows>,
+) {
+    let mut to = playback.position;
+    if key.just_pressed(KeyCode::Space) {
+        if playback.is_finished() {
+            to = Duration::ZERO;
+            playback.paused = false;
+        } else {
+            playback.paused = !playback.paused;
+        }
+    } else if key.just_pressed(KeyCode::Left) {
+        to = to.saturating_sub(SEEK);
+    } else if key.just_pressed(KeyCode::Right) {
+        to += SEEK;
+    } else if key.just_pressed(KeyCode::Home) {
+        to = Duration::ZERO;
+    } else if key.just_pressed(KeyCode::End) {
+        to = playback.duration();
+    } else if key.just_pressed(KeyCode::Up) {
+        playback.speed = (playback.speed + 1).min(SPEEDS.len() - 1);
+    } else if key.just_pressed(KeyCode::Down) {
+        playback.speed = playback.speed.saturating_sub(1);
+    }
+
+    if !playback.paused {
+        to += time.delta().mul_f32(SPEEDS[playback.speed]);
+    }
+    playback.seek(&mut board, to);
+    game_time.set(playback.position);
+
+    let title = match notice.get() {
+        Some(text) => format!("{} - {}", playback.title(), text),
+        None => playback.title(),
+    };
+    set_window_title(&mut windows, &title);
+}
+
+// the cursor, apart from the grid it's moved over
+type CursorFilter = (With<PlaybackCursor>, Without<grid::Grid>);
+
+// keep the cursor above the cell it's on
+pub(crate) fn update_playback_cursor(
+    playback: Res<Playback>,
+    tile_size: Res<grid::TileSize>,
+    grid_query: Query<&Transform, With<grid::Grid>>,
+    mut cursor_query: Query<(&mut Transform, &mut Visibility), CursorFilter>,
+) {
+    let grid_transform = match grid_query.get_single() {
+        Ok(grid_transform) => grid_transform,
+        Err(_) => { return; }
+    };
+    let tile_size: Vec2 = (&*tile_size).into();
+
+    for (mut transform, mut visibility) in cursor_query.iter_mut() {
+        match playback.cursor() {
+            Some(cell) => {
+                let position = grid_transform.translation.truncate() + (cell + Vec2::splat(0.5)) * tile_size;
+                transform.translation = position.extend(10.);
+                visibility.is_visible = true;
+            }
+            None => { visibility.is_visible = false; }
+        }
+    }
+}
+
+pub(crate) fn despawn_playback(
+    mut cmd: Commands,
+    cursor_query: Query<Entity, With<PlaybackCursor>>,
+) {
+    for entity in cursor_query.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
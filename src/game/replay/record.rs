@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::game::{board, Seed};
+use crate::storage;
+use crate::utils;
+
+use super::*;
+
+// directory in the user's data directory
+const DIR: &str = "replays";
+
+/// Resource
+///
+/// Inputs of the running game and the replay of the last one.
+#[derive(Default)]
+pub(crate) struct Recording {
+    start: Option<ReplayStart>,
+    events: Vec<ReplayEvent>,
+    last: Option<Replay>,
+}
+
+impl Recording {
+    /// Keep what the game starts from, on its first input before that is
+    /// applied to the board.
+    #[inline]
+    pub fn begin(&mut self, board: &board::Board, question_marks: bool) {
+        if self.start.is_none() {
+            self.start = Some(ReplayStart::of(board, question_marks));
+        }
+    }
+
+    #[inline]
+    pub fn push(&mut self, time: Duration, (col, row): (usize, usize), button: ReplayButton, action: ReplayAction) {
+        self.events.push(ReplayEvent {
+            time_ms: time.as_millis() as u64,
+            col,
+            row,
+            button,
+            action,
+        });
+    }
+
+    /// What the running game started from, once it has an input.
+    #[inline(always)]
+    pub fn start(&self) -> Option<&ReplayStart> { self.start.as_ref() }
+
+    /// Inputs of the running game so far.
+    #[inline(always)]
+    pub fn events(&self) -> &[ReplayEvent] { &self.events }
+
+    /// Go on with the start and inputs of a resumed game.
+    #[inline]
+    pub fn restore(&mut self, start: Option<ReplayStart>, events: Vec<ReplayEvent>) {
+        self.start = start;
+        self.events = events;
+    }
+
+    /// Replay of the last game that got a cell revealed.
+    #[inline(always)]
+    pub fn last(&self) -> Option<&Replay> { self.last.as_ref() }
+}
+
+// replays of finished games are kept, one file each
+fn replay_path() -> Option<PathBuf> {
+    storage::data_file(DIR).map(|dir| dir.join(utils::file_name("ron")))
+}
+
+pub(crate) fn reset_recording(mut recording: ResMut<Recording>) {
+    recording.start = None;
+    recording.events.clear();
+}
+
+// keep the game that stops running as the last replay, and write it to a
+// file when it's won or lost
+pub(crate) fn save_recording(
+    board: Res<board::Board>,
+    seed: Res<Seed>,
+    mut recording: ResMut<Recording>,
+) {
+    if board.revealed() == 0 {
+        return;
+    }
+    // the start is kept on the first input, a game without any isn't played
+    let start = match recording.start.take() {
+        Some(start) => start,
+        None => { return; }
+    };
+
+    let events = std::mem::take(&mut recording.events);
+    let replay = Replay::new(&board, seed.current(), start, events);
+    if board.is_cleared() || board.is_lost() {
+        match replay_path() {
+            Some(path) => match replay.write(&path) {
+                Ok(()) => info!("replay saved to {}", path.display()),
+                Err(err) => warn!("replay not saved: {}", err),
+            },
+            None => warn!("replay not saved: no data directory"),
+        }
+    }
+    recording.last = Some(replay);
+}
+
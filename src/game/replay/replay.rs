@@ -0,0 +1,285 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::board::{self, Board, Layout, LayoutError, RevealResult};
+use crate::game::grid::GridSize;
+use crate::storage;
+
+use super::*;
+
+/// Mouse button (or buttons) behind an event.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum ReplayButton {
+    Left,
+    Right,
+    Middle,
+    // left and right together
+    Both,
+}
+
+/// What an event does to the cell it's on.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum ReplayAction {
+    Reveal,
+    Flag,
+    Chord,
+}
+
+/// A single input on the grid, timed like the game's clock.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct ReplayEvent {
+    pub time_ms: u64,
+    pub col: usize,
+    pub row: usize,
+    pub button: ReplayButton,
+    pub action: ReplayAction,
+}
+
+impl ReplayEvent {
+    #[inline(always)]
+    pub fn time(&self) -> Duration { Duration::from_millis(self.time_ms) }
+}
+
+/// Every input of a game together with the board it's played on.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Replay {
+    pub version: u32,
+    pub columns: usize,
+    pub rows: usize,
+    // column and row of every mine
+    pub mines: Vec<(usize, usize)>,
+    // none when the mines come from a layout
+    pub seed: Option<u64>,
+    // whether flagging cycles through a question mark
+    pub question_marks: bool,
+    // column and row of the cells revealed and flagged before the first
+    // event, for games that don't start on a covered board
+    pub revealed: Vec<(usize, usize)>,
+    pub flagged: Vec<(usize, usize)>,
+    pub events: Vec<ReplayEvent>,
+}
+
+/// What a game starts from, taken before its first input: the settings that
+/// change how events play out and the cells that are already revealed or
+/// flagged.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct ReplayStart {
+    pub question_marks: bool,
+    pub revealed: Vec<(usize, usize)>,
+    pub flagged: Vec<(usize, usize)>,
+}
+
+impl ReplayStart {
+    pub fn of(board: &Board, question_marks: bool) -> Self {
+        let size = board.size();
+        let cells = |check: fn(&board::Cell) -> bool| {
+            board.cells()
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| check(cell))
+                .map(|(index, _)| size.position_of(index))
+                .collect()
+        };
+        Self {
+            question_marks,
+            revealed: cells(board::Cell::is_revealed),
+            flagged: cells(board::Cell::is_flagged),
+        }
+    }
+}
+
+impl Replay {
+    /// Version of the replay files the game writes.
+    pub const VERSION: u32 = 1;
+
+    /// Replay of a game on the board, once its mines are placed.
+    pub fn new(board: &Board, seed: Option<u64>, start: ReplayStart, events: Vec<ReplayEvent>) -> Self {
+        let size = board.size();
+        let mines = board.cells()
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| cell.is_mine())
+            .map(|(index, _)| size.position_of(index))
+            .collect();
+
+        Self {
+            version: Self::VERSION,
+            columns: size.columns(),
+            rows: size.rows(),
+            mines,
+            seed,
+            question_marks: start.question_marks,
+            revealed: start.revealed,
+            flagged: start.flagged,
+            events,
+        }
+    }
+
+    #[inline(always)]
+    pub fn size(&self) -> GridSize { GridSize::new(self.columns, self.rows) }
+
+    /// Time of the last event.
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        self.events.last().map_or(Duration::ZERO, |event| event.time())
+    }
+
+    /// The replay's layout, as long as the board and all events make sense.
+    pub fn check(&self) -> Result<Layout, ReplayError> {
+        if self.version > Self::VERSION {
+            return Err(ReplayError::Version(self.version));
+        }
+
+        let size = self.size();
+        if let Some(&(col, row)) = self.mines.iter().find(|&&(col, row)| col >= size.columns() || row >= size.rows()) {
+            return Err(ReplayError::Layout(LayoutError::OutOfBounds((col, row))));
+        }
+        let mines = self.mines
+            .iter()
+            .map(|&(col, row)| size.index_of(col, row))
+            .collect();
+        let layout = Layout::new(size, mines).map_err(ReplayError::Layout)?;
+
+        let inside = |&&(col, row): &&(usize, usize)| col < size.columns() && row < size.rows();
+        if let Some(&cell) = self.revealed.iter().chain(&self.flagged).find(|cell| !inside(cell)) {
+            return Err(ReplayError::Start(cell));
+        }
+        let board = Board::with_layout(&layout);
+        if let Some(&cell) = self.revealed.iter().find(|&&(col, row)| {
+            board.cell_at(size.index_of(col, row)).is_mine() || self.flagged.contains(&(col, row))
+        }) {
+            return Err(ReplayError::Start(cell));
+        }
+
+        let mut time_ms = 0;
+        for (index, event) in self.events.iter().enumerate() {
+            if event.col >= size.columns() || event.row >= size.rows() {
+                return Err(ReplayError::OutOfBounds(index));
+            }
+            if event.time_ms < time_ms {
+                return Err(ReplayError::Unordered(index));
+            }
+            time_ms = event.time_ms;
+        }
+        Ok(layout)
+    }
+
+    /// Board the first event is played on, the layout's with the cells of
+    /// the start revealed and flagged.
+    pub fn start(&self, layout: &Layout) -> Board {
+        let size = layout.size();
+        let mut board = Board::with_layout(layout);
+        for &(col, row) in &self.flagged {
+            board.cycle_mark(size.index_of(col, row), false);
+        }
+        for &(col, row) in &self.revealed {
+            board.reveal(size.index_of(col, row));
+        }
+        board
+    }
+
+    /// Play an event on the board, the same way a click in the game does.
+    pub fn apply(&self, board: &mut Board, event: &ReplayEvent) {
+        let index = board.size().index_of(event.col, event.row);
+        let result = match event.action {
+            ReplayAction::Reveal => board.reveal(index),
+            ReplayAction::Chord => board.chord(index),
+            ReplayAction::Flag => {
+                board.cycle_mark(index, self.question_marks);
+                RevealResult::None
+            }
+        };
+
+        if let RevealResult::Exploded = result {
+            board.reveal_mines();
+        } else if board.is_cleared() {
+            board.flag_mines();
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self, ReplayError> {
+        storage::read_ron::<Self>(path)
+            .map_err(ReplayError::Read)?
+            .ok_or_else(|| ReplayError::Read("no such file".to_string()))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        storage::write_ron(path, self)
+    }
+
+    /// Read a replay of the game, or one of Minesweeper X (.rmv) or
+    /// Minesweeper Arbiter (.avf), told apart by the extension.
+    pub fn load(path: &Path) -> Result<(Self, ReplayInfo), ReplayError> {
+        let extension = path.extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase());
+
+        match extension.as_deref() {
+            Some("rmv") => read_rmv(&fs::read(path).map_err(|err| ReplayError::Read(err.to_string()))?),
+            Some("avf") => read_avf(&fs::read(path).map_err(|err| ReplayError::Read(err.to_string()))?),
+            _ => {
+                let replay = Self::read(path)?;
+                let info = ReplayInfo {
+                    format: "replay",
+                    version: replay.version,
+                    program: None,
+                    player: None,
+                    time: replay.duration(),
+                };
+                Ok((replay, info))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ReplayInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} version {}", self.format, self.version)?;
+        if let Some(program) = &self.program {
+            write!(f, " ({})", program)?;
+        }
+        write!(f, ", player {}", self.player.as_deref().unwrap_or("unknown"))?;
+        write!(f, ", time {:.3}", self.time.as_secs_f32())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum ReplayError {
+    Read(String),
+    // the file ends before all of it is read
+    Truncated,
+    // the file isn't of the format it's read as
+    Format(&'static str),
+    // a version or content the game can't play
+    Unsupported(String),
+    // written by a newer game
+    Version(u32),
+    Layout(board::LayoutError),
+    // cell of the start that is outside of the board, or revealed on a mine
+    // or a flag
+    Start((usize, usize)),
+    // event at the index is outside of the board
+    OutOfBounds(usize),
+    // event at the index happens before the one in front of it
+    Unordered(usize),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Read(err) => write!(f, "{}", err),
+            ReplayError::Truncated => write!(f, "file ends too early"),
+            ReplayError::Format(format) => write!(f, "not an {} file", format),
+            ReplayError::Unsupported(what) => write!(f, "{} is not supported", what),
+            ReplayError::Version(version) => write!(f, "version {} is not supported, up to {} is", version, Replay::VERSION),
+            ReplayError::Layout(err) => write!(f, "{}", err),
+            ReplayError::Start((col, row)) => write!(f, "starting cell at {},{} can't be revealed or flagged", col, row),
+            ReplayError::OutOfBounds(index) => write!(f, "event {} is outside of the board", index + 1),
+            ReplayError::Unordered(index) => write!(f, "event {} happens before the one in front of it", index + 1),
+        }
+    }
+}
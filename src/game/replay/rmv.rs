@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use crate::game::board;
+use crate::game::grid::GridSize;
+
+use super::*;
+use super::import::{replay_from_mouse, MouseEvent, MouseKind, Reader};
+
+const MAGIC: &[u8] = b"*rmv";
+// the only version of the file format there is
+const FORMAT_VERSION: u16 = 1;
+
+/// Read a Minesweeper X (RMV) replay. After the header with the sizes of
+/// its parts come the result and the version of the clone, the player's
+/// info, the board with a column and row byte per mine, flags and
+/// properties, and then the events until the game ends.
+pub(crate) fn read_rmv(bytes: &[u8]) -> Result<(Replay, ReplayInfo), ReplayError> {
+    let mut reader = Reader::new(bytes);
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(ReplayError::Format("RMV"));
+    }
+    let format_version = reader.u16()?;
+    if format_version != FORMAT_VERSION {
+        return Err(ReplayError::Unsupported(format!("RMV format version {}", format_version)));
+    }
+
+    reader.skip(4)?;
+    let result_size = reader.u16()? as usize;
+    let version_size = reader.u16()? as usize;
+    reader.skip(4)?;
+    let preflags_size = reader.u16()? as usize;
+    let properties_size = reader.u16()? as usize;
+    let extension_size = reader.u16()? as usize;
+    reader.skip(2)?;
+    reader.skip(extension_size)?;
+
+    reader.skip(result_size)?;
+    let program = reader.text(version_size)?;
+
+    // name, nickname, country and token, as far as they are there
+    let player_info = reader.u16()?;
+    let mut player = None;
+    for i in 0..player_info {
+        let len = reader.u8()? as usize;
+        let text = reader.text(len)?;
+        if i == 0 && !text.is_empty() {
+            player = Some(text);
+        }
+    }
+
+    reader.skip(4)?;
+    let columns = reader.u8()? as usize;
+    let rows = reader.u8()? as usize;
+    let count = reader.u16()? as usize;
+    let size = GridSize::new(columns, rows);
+    let mut mines = Vec::with_capacity(count);
+    for _ in 0..count {
+        let col = reader.u8()? as usize;
+        let row = reader.u8()? as usize;
+        if col >= columns || row >= rows {
+            return Err(ReplayError::Layout(board::LayoutError::OutOfBounds((col, row))));
+        }
+        mines.push((col, row));
+    }
+
+    reader.skip(preflags_size)?;
+    let properties = reader.take(properties_size)?;
+    let question_marks = properties.first().is_some_and(|&marks| marks != 0);
+
+    let mut mouse = Vec::new();
+    loop {
+        match reader.u8()? {
+            // nothing happened, only time passed
+            0 => { reader.skip(4)?; }
+            kind @ 1..=7 => {
+                let time_ms = reader.u24()? as u64;
+                let x = reader.u16()?;
+                let y = reader.u16()?;
+                let kind = match kind {
+                    1 => MouseKind::Move,
+                    2 => MouseKind::LeftDown,
+                    3 => MouseKind::LeftUp,
+                    4 => MouseKind::RightDown,
+                    5 => MouseKind::RightUp,
+                    6 => MouseKind::MiddleDown,
+                    _ => MouseKind::MiddleUp,
+                };
+                mouse.push(MouseEvent { time_ms, x, y, kind });
+            }
+            // changes to the board, they follow from the mouse events
+            9..=14 | 18..=27 => { reader.skip(2)?; }
+            // the game is over
+            15..=17 => { break; }
+            kind => {
+                return Err(ReplayError::Unsupported(format!("RMV event {}", kind)));
+            }
+        }
+    }
+
+    let replay = replay_from_mouse(size, &mines, question_marks, &mouse);
+    let info = ReplayInfo {
+        format: "RMV",
+        version: format_version as u32,
+        program: Some(program).filter(|program| !program.is_empty()),
+        player,
+        time: mouse.last().map_or(Duration::ZERO, |event| Duration::from_millis(event.time_ms)),
+    };
+    Ok((replay, info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 8x8 board with a mine in the top right corner and question marks on,
+    // a left click on the top left cell at half a second
+    fn rmv() -> Vec<u8> {
+        let program = b"MSX 0.97";
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&FORMAT_VERSION.to_be_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        // result and version sizes
+        bytes.extend_from_slice(&[0, 2, 0, program.len() as u8]);
+        bytes.extend_from_slice(&[0; 4]);
+        // preflags, properties and extension sizes
+        bytes.extend_from_slice(&[0, 0, 0, 1, 0, 0]);
+        bytes.extend_from_slice(&[0; 2]);
+        bytes.extend_from_slice(b"ok");
+        bytes.extend_from_slice(program);
+        // name and nickname
+        bytes.extend_from_slice(&[0, 2, 4]);
+        bytes.extend_from_slice(b"anna");
+        bytes.extend_from_slice(&[0]);
+        bytes.extend_from_slice(&[0; 4]);
+        // columns, rows, mines and a column and row from the top per mine
+        bytes.extend_from_slice(&[8, 8, 0, 1, 7, 0]);
+        bytes.extend_from_slice(&[1]);
+        // kind, time, x and y
+        bytes.extend_from_slice(&[2, 0, 1, 244, 0, 8, 0, 8]);
+        bytes.extend_from_slice(&[3, 0, 1, 244, 0, 8, 0, 8]);
+        bytes.extend_from_slice(&[15]);
+        bytes
+    }
+
+    #[test]
+    fn read_valid() {
+        let (replay, info) = read_rmv(&rmv()).unwrap();
+        assert_eq!(info.format, "RMV");
+        assert_eq!(info.version, 1);
+        assert_eq!(info.program.as_deref(), Some("MSX 0.97"));
+        assert_eq!(info.player.as_deref(), Some("anna"));
+        assert_eq!(info.time, Duration::from_millis(500));
+
+        assert_eq!((replay.columns, replay.rows), (8, 8));
+        assert_eq!(replay.mines, vec![(7, 7)]);
+        assert!(replay.question_marks);
+        assert_eq!(replay.events.len(), 1);
+        let event = &replay.events[0];
+        assert_eq!((event.col, event.row, event.time_ms), (0, 7, 500));
+        assert_eq!((event.button, event.action), (ReplayButton::Left, ReplayAction::Reveal));
+        assert!(replay.check().is_ok());
+    }
+
+    #[test]
+    fn read_unsupported_version() {
+        let mut bytes = rmv();
+        bytes[5] = 2;
+        assert!(matches!(read_rmv(&bytes), Err(ReplayError::Unsupported(_))));
+
+        bytes[0] = b'#';
+        assert!(matches!(read_rmv(&bytes), Err(ReplayError::Format(_))));
+    }
+}
@@ -0,0 +1,285 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::Preset;
+use crate::storage;
+
+use super::*;
+
+const FILE: &str = "saved_game.ron";
+
+/// A game that was running when the game was closed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct SavedGame {
+    pub difficulty: Preset,
+    pub columns: usize,
+    pub rows: usize,
+    pub mines: Vec<usize>,
+    pub revealed: Vec<usize>,
+    pub flagged: Vec<usize>,
+    pub questioned: Vec<usize>,
+    pub elapsed_ms: u64,
+    pub seed: Option<u64>,
+    pub clicks: metrics::Clicks,
+    // what the replay of the game starts from, none before the first input
+    pub start: Option<replay::ReplayStart>,
+    pub events: Vec<replay::ReplayEvent>,
+    pub hints: u32,
+}
+
+impl SavedGame {
+    #[inline]
+    fn path() -> Option<PathBuf> {
+        storage::data_file(FILE)
+    }
+
+    /// Read the saved game, a file that can't be read is removed.
+    fn load() -> Option<Self> {
+        let path = Self::path()?;
+        match storage::read_ron::<Self>(&path) {
+            Ok(saved) => saved,
+            Err(err) => {
+                warn!("saved game {} can't be read: {}", path.display(), err);
+                let _ = fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or_else(|| "no data directory".to_string())?;
+        storage::write_ron(&path, self)
+    }
+
+    fn remove() {
+        if let Some(path) = Self::path() {
+            let _ = fs::remove_file(&path);
+        }
+    }
+
+    fn difficulty(&self) -> Difficulty {
+        match self.difficulty {
+            Preset::Beginner => Difficulty::Beginner,
+            Preset::Intermediate => Difficulty::Intermediate,
+            Preset::Expert => Difficulty::Expert,
+            Preset::Custom => Difficulty::Custom {
+                size: grid::GridSize::new(self.columns, self.rows),
+                mines: self.mines.len(),
+            },
+        }
+    }
+
+    /// The board as it was, marks first so revealing doesn't open them.
+    fn board(&self) -> Result<board::Board, String> {
+        let size = grid::GridSize::new(self.columns, self.rows);
+        let layout = board::Layout::new(size, self.mines.clone()).map_err(|err| err.to_string())?;
+        let cells = self.revealed.iter().chain(&self.flagged).chain(&self.questioned);
+        if cells.clone().any(|&index| index >= size.capacity()) {
+            return Err("cell outside of the board".to_string());
+        }
+
+        let mut board = board::Board::with_layout(&layout);
+        for &index in &self.flagged {
+            board.cycle_mark(index, true);
+        }
+        for &index in &self.questioned {
+            board.cycle_mark(index, true);
+            board.cycle_mark(index, true);
+        }
+        for &index in &self.revealed {
+            if let board::RevealResult::Exploded = board.reveal(index) {
+                return Err("a revealed cell is a mine".to_string());
+            }
+        }
+        Ok(board)
+    }
+}
+
+/// Resource
+///
+/// The game saved on the last exit, and whether the running game is one.
+#[derive(Default)]
+pub(crate) struct Resume {
+    saved: Option<SavedGame>,
+    // resume the saved game with the next game
+    restore: bool,
+    // the running game is resumed
+    current: bool,
+}
+
+impl Resume {
+    pub fn load() -> Self {
+        Self {
+            saved: SavedGame::load(),
+            ..default()
+        }
+    }
+
+    /// Whether the running game is resumed.
+    #[inline(always)]
+    pub fn is_current(&self) -> bool { self.current }
+
+    fn text(&self, font: Handle<Font>, colors: &ui::Colors) -> Text {
+        let mut lines = ui::OverlayLines::new(font);
+        lines.line("unfinished game", colors.text).empty();
+        if let Some(saved) = &self.saved {
+            let elapsed = Duration::from_millis(saved.elapsed_ms);
+            lines
+                .line(leaderboard::board_name(&saved.difficulty()), colors.text)
+                .line(format!("{} cells revealed", saved.revealed.len()), colors.text)
+                .line(format!("{:.1} seconds", elapsed.as_secs_f32()), colors.text);
+        }
+        lines
+            .empty()
+            .line("enter  resume", colors.dark)
+            .line("N      new game", colors.dark);
+        lines.build()
+    }
+}
+
+// offer the saved game once the first game is set up
+pub(super) fn offer_resume(
+    resume: Res<Resume>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if resume.saved.is_some() && !resume.restore {
+        let _ = state.push(GameState::Resume);
+    }
+}
+
+pub(super) fn spawn_resume_dialog(
+    mut cmd: Commands,
+    asset_server: Res<AssetServer>,
+    colors: Res<ui::Colors>,
+    resume: Res<Resume>,
+) {
+    let text = resume.text(asset_server.get_handle(ui::FONT), &colors);
+    ui::Overlay::spawn(&mut cmd, colors.overlay, text);
+}
+
+// enter resumes the saved game, N drops it and keeps the new one
+pub(super) fn handle_resume_input(
+    key: Res<Input<KeyCode>>,
+    mut resume: ResMut<Resume>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if key.just_pressed(KeyCode::Return) {
+        resume.restore = true;
+        let _ = state.replace(GameState::Start);
+    } else if key.just_pressed(KeyCode::N) {
+        resume.saved = None;
+        SavedGame::remove();
+        let _ = state.pop();
+    }
+}
+
+// put the saved game back in place of the new one, once the new one is set up
+#[allow(clippy::too_many_arguments)]
+pub(super) fn restore_game(
+    mut resume: ResMut<Resume>,
+    mut difficulty: ResMut<Difficulty>,
+    mut seed: ResMut<Seed>,
+    mut board: ResMut<board::Board>,
+    mut game_time: ResMut<GameTime>,
+    mut clicks: ResMut<metrics::Clicks>,
+    mut recording: ResMut<replay::Recording>,
+    mut unranked: ResMut<Unranked>,
+    mut hints: ResMut<hint::Hints>,
+    mut notice: ResMut<Notice>,
+) {
+    resume.current = false;
+    if !resume.restore {
+        return;
+    }
+    resume.restore = false;
+
+    let saved = match resume.saved.take() {
+        Some(saved) => saved,
+        None => { return; }
+    };
+    SavedGame::remove();
+
+    match saved.board() {
+        Ok(restored) => {
+            info!("resume game");
+            *board = restored;
+            *difficulty = saved.difficulty();
+            seed.restore(saved.seed);
+            game_time.set(Duration::from_millis(saved.elapsed_ms));
+            *clicks = saved.clicks;
+            recording.restore(saved.start, saved.events);
+            hints.restore(saved.hints);
+            resume.current = true;
+            unranked.0 = Some("resumed games");
+        }
+        Err(err) => {
+            warn!("saved game can't be resumed: {}", err);
+            notice.set("saved game can't be resumed");
+        }
+    }
+}
+
+// keep a running game when the window is closed or escape is pressed, runs
+// after the exit is asked for and before the app stops
+#[allow(clippy::too_many_arguments)]
+pub(super) fn save_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    state: Res<State<GameState>>,
+    difficulty: Res<Difficulty>,
+    board: Res<board::Board>,
+    game_time: Res<GameTime>,
+    seed: Res<Seed>,
+    clicks: Res<metrics::Clicks>,
+    recording: Res<replay::Recording>,
+    hints: Res<hint::Hints>,
+) {
+    if exit_events.iter().last().is_none() {
+        return;
+    }
+
+    // the game may be paused underneath a dialog
+    let running = *state.current() == GameState::Run || state.inactives().contains(&GameState::Run);
+    if !running || board.revealed() == 0 || board.is_cleared() || board.is_lost() {
+        return;
+    }
+
+    let size = board.size();
+    let cells = |check: fn(&board::Cell) -> bool| {
+        board.cells()
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| check(cell))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>()
+    };
+    let saved = SavedGame {
+        difficulty: match *difficulty {
+            Difficulty::Beginner => Preset::Beginner,
+            Difficulty::Intermediate => Preset::Intermediate,
+            Difficulty::Expert => Preset::Expert,
+            Difficulty::Custom { .. } => Preset::Custom,
+        },
+        columns: size.columns(),
+        rows: size.rows(),
+        mines: cells(board::Cell::is_mine),
+        revealed: cells(board::Cell::is_revealed),
+        flagged: cells(board::Cell::is_flagged),
+        questioned: cells(board::Cell::is_question),
+        elapsed_ms: game_time.elapsed().as_millis() as u64,
+        seed: seed.current(),
+        clicks: *clicks,
+        start: recording.start().cloned(),
+        events: recording.events().to_vec(),
+        hints: hints.used(),
+    };
+
+    match saved.save() {
+        Ok(()) => info!("game saved, it can be resumed on the next start"),
+        Err(err) => warn!("game not saved: {}", err),
+    }
+}
@@ -0,0 +1,478 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+use crate::utils;
+
+use super::*;
+
+const FILE: &str = "stats.ron";
+const CSV_FILE: &str = "stats.csv";
+// every game that is played, one per line
+const GAMES_FILE: &str = "games.csv";
+const GAMES_HEADER: &str = "date,difficulty,result,time_ms,3bv,solved_3bv,3bv_s,ioe,rqp,correctness,left,right,chord,wasted,seed,resumed,hints";
+const DIFFICULTIES: [&str; 4] = ["beginner", "intermediate", "expert", "custom"];
+
+/// Lifetime numbers of a single difficulty.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct DifficultyStats {
+    // games with at least one revealed cell
+    pub played: u32,
+    pub won: u32,
+    pub lost: u32,
+    pub streak: u32,
+    pub best_streak: u32,
+    pub best_time_ms: Option<u64>,
+    // of all won games
+    pub total_time_ms: u64,
+    pub cells_revealed: u64,
+    // games that are saved on exit and resumed later
+    pub resumed: u32,
+}
+
+impl DifficultyStats {
+    /// Share of the finished games that is won, in percent.
+    #[inline]
+    pub fn win_rate(&self) -> Option<f32> {
+        let finished = self.won + self.lost;
+        if finished == 0 {
+            return None;
+        }
+        Some(self.won as f32 * 100. / finished as f32)
+    }
+
+    #[inline]
+    pub fn average_time_ms(&self) -> Option<u64> {
+        if self.won == 0 {
+            return None;
+        }
+        Some(self.total_time_ms / self.won as u64)
+    }
+
+    /// Count a game that got a cell revealed. Any game that isn't won ends
+    /// the streak, also one that is left for a new one. Only ranked games
+    /// can set the best time.
+    fn record(&mut self, board: &board::Board, time: Duration, resumed: bool, ranked: bool) {
+        self.played += 1;
+        if resumed {
+            self.resumed += 1;
+        }
+        self.cells_revealed += board.cells()
+            .iter()
+            .filter(|cell| cell.is_revealed() && !cell.is_mine())
+            .count() as u64;
+
+        if !board.is_cleared() {
+            if board.is_lost() {
+                self.lost += 1;
+            }
+            self.streak = 0;
+            return;
+        }
+
+        let time_ms = time.as_millis() as u64;
+        self.won += 1;
+        self.streak += 1;
+        self.best_streak = self.best_streak.max(self.streak);
+        if ranked {
+            self.best_time_ms = Some(self.best_time_ms.map_or(time_ms, |best| best.min(time_ms)));
+        }
+        self.total_time_ms += time_ms;
+    }
+}
+
+/// Resource
+///
+/// Lifetime numbers per difficulty, all custom difficulties together, kept
+/// in the user's data directory.
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct Stats {
+    difficulties: BTreeMap<String, DifficultyStats>,
+}
+
+impl Stats {
+    #[inline]
+    fn path() -> Option<PathBuf> {
+        storage::data_file(FILE)
+    }
+
+    /// Read the stats file. A file that can't be read is moved aside, so the
+    /// next game doesn't overwrite it.
+    pub fn load() -> Self {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => { return Self::default(); }
+        };
+
+        match storage::read_ron::<Self>(&path) {
+            Ok(stats) => stats.unwrap_or_default(),
+            Err(err) => {
+                let backup = path.with_extension("ron.bak");
+                warn!("stats {} can't be read: {}, moved to {}", path.display(), err, backup.display());
+                let _ = fs::rename(&path, &backup);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or_else(|| "no data directory".to_string())?;
+        storage::write_ron(&path, self)
+    }
+
+    #[inline]
+    pub fn get(&self, difficulty: &str) -> DifficultyStats {
+        self.difficulties.get(difficulty).cloned().unwrap_or_default()
+    }
+
+    #[inline]
+    fn get_mut(&mut self, difficulty: &Difficulty) -> &mut DifficultyStats {
+        self.difficulties.entry(stats_name(difficulty).to_string()).or_default()
+    }
+
+    #[inline]
+    pub fn reset(&mut self) {
+        self.difficulties.clear();
+    }
+
+    /// Write the stats of every difficulty as CSV, returns where it went.
+    pub fn export_csv(&self) -> Result<PathBuf, String> {
+        let path = storage::data_file(CSV_FILE).ok_or_else(|| "no data directory".to_string())?;
+
+        let mut csv = String::from("difficulty,played,won,lost,win_rate,streak,best_streak,best_time_ms,average_time_ms,cells_revealed,resumed\n");
+        for name in DIFFICULTIES {
+            let stats = self.get(name);
+            let optional = |value: Option<u64>| value.map_or(String::new(), |value| value.to_string());
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{},{}\n",
+                name,
+                stats.played,
+                stats.won,
+                stats.lost,
+                stats.win_rate().map_or(String::new(), |rate| format!("{:.1}", rate)),
+                stats.streak,
+                stats.best_streak,
+                optional(stats.best_time_ms),
+                optional(stats.average_time_ms()),
+                stats.cells_revealed,
+                stats.resumed,
+            ));
+        }
+
+        storage::write_atomic(&path, csv.as_bytes())
+            .map_err(|err| format!("can't write {}: {}", path.display(), err))?;
+        Ok(path)
+    }
+}
+
+#[inline]
+fn stats_name(difficulty: &Difficulty) -> &'static str {
+    match difficulty {
+        Difficulty::Beginner => DIFFICULTIES[0],
+        Difficulty::Intermediate => DIFFICULTIES[1],
+        Difficulty::Expert => DIFFICULTIES[2],
+        Difficulty::Custom { .. } => DIFFICULTIES[3],
+    }
+}
+
+/// Resource
+///
+/// Difficulty that the statistics screen shows.
+#[derive(Default)]
+pub(crate) struct StatsView {
+    difficulty: usize,
+    // reset was pressed once and needs confirming
+    confirm_reset: bool,
+    message: Option<String>,
+}
+
+impl StatsView {
+    fn text(&self, stats: &Stats, font: Handle<Font>, colors: &ui::Colors, bindings: &Bindings) -> Text {
+        let name = DIFFICULTIES[self.difficulty];
+        let current = stats.get(name);
+        let optional_time = |time: Option<u64>| {
+            time.map_or("-".to_string(), |time| format!("{}.{:03}", time / 1000, time % 1000))
+        };
+
+        let mut lines = ui::OverlayLines::new(font);
+        lines
+            .line(format!("statistics {}", name), colors.text)
+            .empty()
+            .line(format!("played       {:>8}", current.played), colors.text)
+            .line(format!("won          {:>8}", current.won), colors.text)
+            .line(format!("lost         {:>8}", current.lost), colors.text)
+            .line(format!("win rate     {:>8}", current.win_rate().map_or("-".to_string(), |rate| format!("{:.1}%", rate))), colors.text)
+            .line(format!("streak       {:>8}", current.streak), colors.text)
+            .line(format!("best streak  {:>8}", current.best_streak), colors.text)
+            .line(format!("best time    {:>8}", optional_time(current.best_time_ms)), colors.text)
+            .line(format!("average time {:>8}", optional_time(current.average_time_ms())), colors.text)
+            .line(format!("cells        {:>8}", current.cells_revealed), colors.text)
+            .line(format!("resumed      {:>8}", current.resumed), colors.text)
+            .empty();
+
+        if self.confirm_reset {
+            lines.line("R again to reset all", colors.error);
+        } else if let Some(message) = &self.message {
+            lines.line(message.as_str(), colors.highlight);
+        } else {
+            lines.empty();
+        }
+
+        lines
+            .line("left/right  difficulty", colors.dark)
+            .line("R reset  E export csv", colors.dark)
+            .line(format!("{:<12}close", format!("{:?}", bindings.stats)), colors.dark);
+        lines.build()
+    }
+}
+
+/// Add a game and how efficiently it's played to the games file.
+fn append_game(
+    difficulty: &Difficulty,
+    board: &board::Board,
+    metrics: &metrics::Metrics,
+    seed: Option<u64>,
+    resumed: bool,
+    hints: u32,
+) -> Result<(), String> {
+    let path = storage::data_file(GAMES_FILE).ok_or_else(|| "no data directory".to_string())?;
+
+    let result = if board.is_cleared() {
+        "won"
+    } else if board.is_lost() {
+        "lost"
+    } else {
+        "abandoned"
+    };
+    let clicks = metrics.clicks;
+    let line = format!(
+        "{},{},{},{},{},{},{:.3},{:.3},{:.3},{:.3},{},{},{},{},{},{},{}",
+        utils::today(),
+        leaderboard::board_name(difficulty),
+        result,
+        metrics.time.as_millis(),
+        metrics.bbbv,
+        metrics.solved_bbbv,
+        metrics.bbbv_per_second(),
+        metrics.ioe(),
+        metrics.rqp(),
+        metrics.correctness(),
+        clicks.left,
+        clicks.right,
+        clicks.chord,
+        clicks.wasted,
+        seed.map_or(String::new(), |seed| seed.to_string()),
+        resumed,
+        hints,
+    );
+
+    storage::append_line(&path, GAMES_HEADER, &line)
+        .map_err(|err| format!("can't write {}: {}", path.display(), err))
+}
+
+// count every game that got a cell revealed once it stops running, whether
+// it's won, lost or left for a new one
+#[allow(clippy::too_many_arguments)]
+pub(super) fn record_game(
+    difficulty: Res<Difficulty>,
+    game_time: Res<GameTime>,
+    board: Res<board::Board>,
+    clicks: Res<metrics::Clicks>,
+    seed: Res<Seed>,
+    resume: Res<resume::Resume>,
+    unranked: Res<Unranked>,
+    hints: Res<hint::Hints>,
+    mut stats: ResMut<Stats>,
+) {
+    if board.revealed() == 0 {
+        return;
+    }
+
+    let metrics = metrics::Metrics::new(&board, game_time.elapsed(), *clicks);
+    let resumed = resume.is_current();
+    if let Err(err) = append_game(&difficulty, &board, &metrics, seed.current(), resumed, hints.used()) {
+        warn!("game not recorded: {}", err);
+    }
+
+    stats.get_mut(&difficulty).record(&board, game_time.elapsed(), resumed, unranked.0.is_none());
+    if let Err(err) = stats.save() {
+        warn!("stats not saved: {}", err);
+    }
+}
+
+// show the statistics of the current difficulty on T key press
+pub(super) fn open_stats(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    difficulty: Res<Difficulty>,
+    mut view: ResMut<StatsView>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !key.just_released(bindings.stats) {
+        return;
+    }
+    match state.current() {
+        GameState::Run | GameState::Over | GameState::Won => {}
+        _ => { return; }
+    }
+
+    *view = StatsView {
+        difficulty: DIFFICULTIES.iter().position(|name| *name == stats_name(&difficulty)).unwrap_or(0),
+        ..default()
+    };
+    let _ = state.push(GameState::Stats);
+}
+
+pub(super) fn spawn_stats(
+    mut cmd: Commands,
+    asset_server: Res<AssetServer>,
+    colors: Res<ui::Colors>,
+    bindings: Res<Bindings>,
+    stats: Res<Stats>,
+    view: Res<StatsView>,
+) {
+    let text = view.text(&stats, asset_server.get_handle(ui::FONT), &colors, &bindings);
+    ui::Overlay::spawn(&mut cmd, colors.overlay, text);
+}
+
+// left and right go through the difficulties, R twice resets everything,
+// E exports to CSV and the same key or enter closes it
+pub(super) fn handle_stats_input(
+    key: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    mut stats: ResMut<Stats>,
+    mut view: ResMut<StatsView>,
+    mut state: ResMut<State<GameState>>,
+) {
+    // opened on release, so the same key press can't close it right away
+    if key.just_pressed(bindings.stats) || key.just_pressed(KeyCode::Return) {
+        let _ = state.pop();
+        return;
+    }
+
+    if key.just_pressed(KeyCode::Right) {
+        view.difficulty = (view.difficulty + 1) % DIFFICULTIES.len();
+        view.confirm_reset = false;
+    } else if key.just_pressed(KeyCode::Left) {
+        view.difficulty = (view.difficulty + DIFFICULTIES.len() - 1) % DIFFICULTIES.len();
+        view.confirm_reset = false;
+    } else if key.just_pressed(KeyCode::R) {
+        if view.confirm_reset {
+            stats.reset();
+            if let Err(err) = stats.save() {
+                warn!("stats not saved: {}", err);
+            }
+            view.message = Some("statistics reset".to_string());
+        }
+        view.confirm_reset = !view.confirm_reset;
+    } else if key.just_pressed(KeyCode::E) {
+        view.confirm_reset = false;
+        view.message = Some(match stats.export_csv() {
+            Ok(path) => {
+                info!("stats exported to {}", path.display());
+                format!("exported {}", CSV_FILE)
+            }
+            Err(err) => {
+                warn!("stats not exported: {}", err);
+                "export failed".to_string()
+            }
+        });
+    }
+}
+
+pub(super) fn update_stats(
+    asset_server: Res<AssetServer>,
+    colors: Res<ui::Colors>,
+    bindings: Res<Bindings>,
+    stats: Res<Stats>,
+    view: Res<StatsView>,
+    mut text_query: Query<&mut Text, With<ui::OverlayText>>,
+) {
+    if !view.is_changed() && !stats.is_changed() {
+        return;
+    }
+
+    for mut text in text_query.iter_mut() {
+        *text = view.text(&stats, asset_server.get_handle(ui::FONT), &colors, &bindings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a row of four cells with mines on both ends
+    fn board() -> board::Board {
+        let mut board = board::Board::new(grid::GridSize::new(4, 1), 2);
+        board.place_mines(&[0, 3]);
+        board
+    }
+
+    fn won() -> board::Board {
+        let mut board = board();
+        board.reveal(1);
+        board.reveal(2);
+        board
+    }
+
+    #[test]
+    fn won_games_keep_the_streak() {
+        let mut stats = DifficultyStats::default();
+        stats.record(&won(), Duration::from_millis(3000), false, true);
+        stats.record(&won(), Duration::from_millis(2000), false, true);
+        assert_eq!(stats.played, 2);
+        assert_eq!(stats.won, 2);
+        assert_eq!(stats.streak, 2);
+        assert_eq!(stats.best_streak, 2);
+        assert_eq!(stats.best_time_ms, Some(2000));
+        assert_eq!(stats.average_time_ms(), Some(2500));
+        assert_eq!(stats.cells_revealed, 4);
+    }
+
+    #[test]
+    fn lost_game_resets_streak() {
+        let mut lost = board();
+        lost.reveal(0);
+
+        let mut stats = DifficultyStats::default();
+        stats.record(&won(), Duration::from_millis(1000), false, true);
+        stats.record(&lost, Duration::from_millis(1000), false, true);
+        assert_eq!(stats.lost, 1);
+        assert_eq!(stats.streak, 0);
+        assert_eq!(stats.best_streak, 1);
+        assert_eq!(stats.win_rate(), Some(50.));
+    }
+
+    #[test]
+    fn abandoned_game_resets_streak() {
+        let mut abandoned = board();
+        abandoned.reveal(1);
+
+        let mut stats = DifficultyStats::default();
+        stats.record(&won(), Duration::from_millis(1000), false, true);
+        stats.record(&won(), Duration::from_millis(1000), false, true);
+        stats.record(&abandoned, Duration::from_millis(1000), false, true);
+        assert_eq!(stats.played, 3);
+        assert_eq!(stats.won, 2);
+        assert_eq!(stats.lost, 0);
+        assert_eq!(stats.streak, 0);
+        assert_eq!(stats.best_streak, 2);
+        assert_eq!(stats.cells_revealed, 5);
+    }
+
+    #[test]
+    fn resumed_game_has_no_best_time() {
+        let mut stats = DifficultyStats::default();
+        stats.record(&won(), Duration::from_millis(1000), true, false);
+        assert_eq!(stats.won, 1);
+        assert_eq!(stats.resumed, 1);
+        assert_eq!(stats.best_time_ms, None);
+        assert_eq!(stats.average_time_ms(), Some(1000));
+    }
+}
@@ -1,4 +1,10 @@
 use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+pub const DIGITS_TEXTURE_ATLAS: &str = "digits";
+
+// sprite index of the minus sign, right after 0-9
+const MINUS: usize = 10;
 
 #[derive(Component)]
 pub(crate) enum DigitDisplay {
@@ -8,19 +14,60 @@ pub(crate) enum DigitDisplay {
 
 #[derive(Component)]
 pub(crate) struct Digits {
-    pub value: u8,
+    pub value: i32,
     digits: [Entity; 3],
 }
 
 impl Digits {
-    pub fn spawn(cmd: &mut Commands, typ: DigitDisplay) -> Entity {
+    pub const DIGIT_WIDTH: f32 = 13.;
+    pub const WIDTH: f32 = Self::DIGIT_WIDTH * 3.;
+    pub const HEIGHT: f32 = 23.;
+
+    pub fn spawn(cmd: &mut Commands, typ: DigitDisplay, texture_atlas: Handle<TextureAtlas>) -> Entity {
+        let digits = [0, 1, 2].map(|i| {
+            let mut sprite = TextureAtlasSprite::new(0);
+            sprite.anchor = Anchor::BottomLeft;
+
+            cmd.spawn_bundle(SpriteSheetBundle {
+                sprite,
+                texture_atlas: texture_atlas.clone(),
+                transform: Transform::from_xyz(i as f32 * Self::DIGIT_WIDTH, 0., 0.),
+                ..Default::default()
+            }).id()
+        });
+
         cmd.spawn_bundle(TransformBundle::default())
             .insert(typ)
-            .push_children(&[])
+            .insert(Self { value: 0, digits })
+            .push_children(&digits)
             .id()
     }
+
+    // sprite indexes of the value, clamped to what fits in 3 digits; negative
+    // values are displayed like -05
+    fn indexes(&self) -> [usize; 3] {
+        let value = self.value.clamp(-99, 999);
+        if value < 0 {
+            let value = -value as usize;
+            return [MINUS, value / 10, value % 10];
+        }
+
+        let value = value as usize;
+        [value / 100, (value / 10) % 10, value % 10]
+    }
 }
 
 pub(crate) fn update_digits_display(
     digits_query: Query<&Digits, Changed<Digits>>,
-) {}
\ No newline at end of file
+    mut sprites_query: Query<&mut TextureAtlasSprite>,
+) {
+    for digits in digits_query.iter() {
+        for (entity, index) in digits.digits.iter().zip(digits.indexes()) {
+            if let Ok(mut sprite) = sprites_query.get_mut(*entity) {
+                if sprite.index != index {
+                    sprite.index = index;
+                }
+            }
+        }
+    }
+}
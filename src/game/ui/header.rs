@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+
+use crate::utils::SpriteSheetBundleBuilder;
+
+use super::*;
+
+/// Panel above the grid, holds the mine counter, smiley and the timer.
+#[derive(Component)]
+pub(crate) struct Header;
+
+impl Header {
+    pub const HEIGHT: f32 = 26.;
+
+    #[inline]
+    pub fn spawn(cmd: &mut Commands, digits_atlas: Handle<TextureAtlas>, ui_sprites: &SpriteSheetBundleBuilder<UiComponent>) -> Entity {
+        let mines = Digits::spawn(cmd, DigitDisplay::Mines, digits_atlas.clone());
+        let smiley = Smiley::spawn(cmd, ui_sprites);
+        let time = Digits::spawn(cmd, DigitDisplay::Time, digits_atlas);
+
+        cmd.spawn_bundle(TransformBundle::default())
+            .insert(Self)
+            .push_children(&[mines, smiley, time])
+            .id()
+    }
+}
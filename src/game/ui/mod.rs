@@ -1,20 +1,41 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
 pub(crate) use digits::*;
 pub(crate) use edge::*;
+pub(crate) use header::*;
+pub(crate) use overlay::*;
+pub(crate) use smiley::*;
 
 mod digits;
 mod edge;
+mod header;
+mod overlay;
+mod smiley;
 
+/// Colour theme, part of the settings.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Colors {
+    pub background: Color,
     pub light: Color,
     pub dark: Color,
+    pub overlay: Color,
+    pub text: Color,
+    pub error: Color,
+    pub highlight: Color,
 }
 
 impl Default for Colors {
     fn default() -> Self {
         Self {
+            background: Color::rgb(200. / 255., 200. / 255., 200. / 255.),
             light: Color::WHITE,
             dark: Color::rgb(140. / 255., 140. / 255., 140. / 255.),
+            overlay: Color::rgba(200. / 255., 200. / 255., 200. / 255., 0.95),
+            text: Color::BLACK,
+            error: Color::rgb(1., 0., 0.),
+            highlight: Color::rgb(0., 0., 1.),
         }
     }
 }
@@ -23,5 +44,9 @@ impl Default for Colors {
 pub enum UiComponent {
     EdgeCorner,
     SmileyButton,
+    SmileyButtonPressed,
+    SmileyHappy,
+    SmileySurprised,
+    SmileyCool,
     SmileyDead,
 }
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+pub const FONT: &str = "FiraMono-Medium.ttf";
+
+/// Panel on top of the whole window, used for dialogs and screens that show
+/// lines of text.
+#[derive(Component)]
+pub(crate) struct Overlay;
+
+#[derive(Component)]
+pub(crate) struct OverlayText;
+
+impl Overlay {
+    pub const FONT_SIZE: f32 = 12.;
+
+    #[inline]
+    pub fn spawn(cmd: &mut Commands, background: Color, text: Text) -> Entity {
+        let text = cmd.spawn_bundle(TextBundle { text, ..default() })
+            .insert(OverlayText)
+            .id();
+
+        cmd.spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(background),
+            ..default()
+        })
+            .insert(Self)
+            .push_children(&[text])
+            .id()
+    }
+}
+
+/// Strip of text at the bottom of the window, the game stays visible above
+/// it.
+#[derive(Component)]
+pub(crate) struct Panel;
+
+#[derive(Component)]
+pub(crate) struct PanelText;
+
+impl Panel {
+    #[inline]
+    pub fn spawn(cmd: &mut Commands, background: Color, text: Text) -> Entity {
+        let text = cmd.spawn_bundle(TextBundle { text, ..default() })
+            .insert(PanelText)
+            .id();
+
+        cmd.spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.),
+                    bottom: Val::Px(0.),
+                    ..default()
+                },
+                justify_content: JustifyContent::Center,
+                padding: Rect::all(Val::Px(4.)),
+                ..default()
+            },
+            color: UiColor(background),
+            ..default()
+        })
+            .insert(Self)
+            .push_children(&[text])
+            .id()
+    }
+}
+
+pub(crate) fn despawn_panel(
+    mut cmd: Commands,
+    panel_query: Query<Entity, With<Panel>>,
+) {
+    for entity in panel_query.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+pub(crate) fn despawn_overlay(
+    mut cmd: Commands,
+    overlay_query: Query<Entity, With<Overlay>>,
+) {
+    for entity in overlay_query.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+/// Builds the text of an overlay, line by line.
+pub(crate) struct OverlayLines {
+    font: Handle<Font>,
+    sections: Vec<TextSection>,
+}
+
+impl OverlayLines {
+    pub fn new(font: Handle<Font>) -> Self {
+        Self {
+            font,
+            sections: Vec::new(),
+        }
+    }
+
+    pub fn line<S: Into<String>>(&mut self, text: S, color: Color) -> &mut Self {
+        let mut value = text.into();
+        value.push('\n');
+
+        self.sections.push(TextSection {
+            value,
+            style: TextStyle {
+                font: self.font.clone(),
+                font_size: Overlay::FONT_SIZE,
+                color,
+            },
+        });
+        self
+    }
+
+    #[inline]
+    pub fn empty(&mut self) -> &mut Self {
+        self.line("", Color::NONE)
+    }
+
+    #[inline]
+    pub fn build(self) -> Text {
+        Text {
+            sections: self.sections,
+            alignment: TextAlignment::default(),
+        }
+    }
+}
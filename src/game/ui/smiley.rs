@@ -0,0 +1,81 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+
+use crate::utils::SpriteSheetBundleBuilder;
+
+use super::*;
+
+#[derive(Component, Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Face {
+    Happy,
+    // a tile is held down
+    Surprised,
+    // game won
+    Cool,
+    // game over
+    Dead,
+}
+
+impl Face {
+    #[inline]
+    pub fn ui_component(&self) -> UiComponent {
+        use Face::*;
+        match self {
+            Happy => UiComponent::SmileyHappy,
+            Surprised => UiComponent::SmileySurprised,
+            Cool => UiComponent::SmileyCool,
+            Dead => UiComponent::SmileyDead,
+        }
+    }
+}
+
+/// Button in the center of the header, restarts the game when clicked.
+#[derive(Component)]
+pub(crate) struct Smiley {
+    pub pressed: bool,
+    face: Entity,
+}
+
+impl Smiley {
+    pub const SIZE: f32 = 26.;
+
+    // position of the face within the button
+    const FACE_X: f32 = 5.;
+    const FACE_Y: f32 = 4.;
+
+    #[inline]
+    pub fn spawn(cmd: &mut Commands, ui_sprites: &SpriteSheetBundleBuilder<UiComponent>) -> Entity {
+        let mut sprite = ui_sprites.get(UiComponent::SmileyHappy).unwrap();
+        sprite.sprite.anchor = Anchor::BottomLeft;
+        sprite.transform = Transform::from_xyz(Self::FACE_X, Self::FACE_Y, 1.);
+        let face = cmd.spawn_bundle(sprite)
+            .insert(Face::Happy)
+            .id();
+
+        let mut sprite = ui_sprites.get(UiComponent::SmileyButton).unwrap();
+        sprite.sprite.anchor = Anchor::BottomLeft;
+        cmd.spawn_bundle(sprite)
+            .insert(Self { pressed: false, face })
+            .push_children(&[face])
+            .id()
+    }
+
+    #[inline(always)]
+    pub fn face(&self) -> Entity { self.face }
+
+    /// Position of the face within the button, it moves a bit when pressed.
+    #[inline]
+    pub fn face_offset(&self) -> Vec2 {
+        if self.pressed {
+            return Vec2::new(Self::FACE_X + 1., Self::FACE_Y - 1.);
+        }
+        Vec2::new(Self::FACE_X, Self::FACE_Y)
+    }
+
+    #[inline]
+    pub fn contains(transform: &GlobalTransform, position: Vec2) -> bool {
+        let min = transform.translation.truncate();
+        let max = min + Vec2::splat(Self::SIZE);
+        position.x >= min.x && position.y >= min.y && position.x < max.x && position.y < max.y
+    }
+}
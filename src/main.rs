@@ -1,21 +1,35 @@
-use std::ops::Deref;
-
 use bevy::prelude::*;
+use bevy::render::camera::Camera2d;
 use bevy::render::texture::ImageType;
 use bevy::sprite::Rect;
 use bevy::window::{WindowId, WindowResized};
 use bevy::winit::WinitWindows;
 
 use crate::game::grid;
+use crate::game::ui;
 use crate::game::ui::UiComponent;
 
+mod cli;
 mod game;
 mod utils;
 mod load;
+mod settings;
+mod storage;
 
 fn main() {
+    let options = match cli::Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("minesweeper: {}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
     let mut app = App::new();
-    app.insert_resource(ClearColor(Color::hex("c8c8c8").unwrap()));
     app.insert_resource(WindowDescriptor {
         title: "Minesweeper".to_string(),
         width: 1.,
@@ -27,6 +41,9 @@ fn main() {
         ..default()
     });
     app.add_plugins(DefaultPlugins);
+    // loaded once logging is set up, so warnings about the file show
+    let settings = settings::Settings::load();
+    app.insert_resource(ClearColor(settings.colors.background));
     app.add_plugin(load::LoadAssetsPlugin);
     app.add_system_set(
         SystemSet::on_enter(load::LoadState::Loaded)
@@ -35,7 +52,9 @@ fn main() {
     app.add_startup_system(setup);
     app.add_system(bevy::input::system::exit_on_esc_system);
     app.add_system(center_camera_on_resize);
+    app.insert_resource(settings);
     app.add_plugin(game::GamePlugin);
+    options.apply(&mut app);
     app.run();
 }
 
@@ -52,8 +71,9 @@ fn setup(
         ImageType::Extension("png"),
     );
 
-    // camera
+    // cameras
     cmd.spawn_bundle(OrthographicCameraBundle::new_2d());
+    cmd.spawn_bundle(UiCameraBundle::default());
 
     // grid tiles
     let tile_image = asset_server.load::<Image, _>("tiles.png");
@@ -62,25 +82,41 @@ fn setup(
     // ui
     let ui_image = asset_server.load::<Image, _>("ui.png");
     load_assets.push(ui_image.clone_untyped());
+
+    let digits_image = asset_server.load::<Image, _>("digits.png");
+    load_assets.push(digits_image.clone_untyped());
+
+    let font = asset_server.load::<Font, _>(ui::FONT);
+    load_assets.push(font.clone_untyped());
 }
 
 fn start_game(
     mut cmd: Commands,
     mut state: ResMut<State<game::GameState>>,
-    tile_size: Res<grid::TileSize>,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
 ) {
     texture_atlases.set_untracked(grid::TILE_TEXTURE_ATLAS, TextureAtlas::from_grid(
         asset_server.get_handle("tiles.png"),
-        tile_size.deref().into(),
+        Vec2::splat(grid::TileSize::TEXTURE),
         grid::Tile::all().len(),
         1,
     ));
 
+    texture_atlases.set_untracked(ui::DIGITS_TEXTURE_ATLAS, TextureAtlas::from_grid(
+        asset_server.get_handle("digits.png"),
+        Vec2::new(ui::Digits::DIGIT_WIDTH, ui::Digits::HEIGHT),
+        11,
+        1,
+    ));
+
     let mut slicer = utils::TextureAtlasSlicer::<UiComponent>::new();
     slicer.add(UiComponent::EdgeCorner, rect(0., 6., 5., 11.));
     slicer.add(UiComponent::SmileyButton, rect(18., 0., 44., 26.));
+    slicer.add(UiComponent::SmileyButtonPressed, rect(44., 0., 70., 26.));
+    slicer.add(UiComponent::SmileyHappy, rect(70., 0., 87., 17.));
+    slicer.add(UiComponent::SmileySurprised, rect(87., 0., 104., 17.));
+    slicer.add(UiComponent::SmileyCool, rect(104., 0., 121., 17.));
     slicer.add(UiComponent::SmileyDead, rect(0., 11., 17., 28.));
 
     let (ui_textures, ui_indexes) = slicer.slice(asset_server.get_handle("ui.png"));
@@ -90,7 +126,7 @@ fn start_game(
 }
 
 fn center_camera_on_resize(
-    mut camera_query: Query<&mut Transform, With<Camera>>,
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
     mut resize_event: EventReader<WindowResized>,
 ) {
     if let Some(resized) = resize_event.iter().last() {
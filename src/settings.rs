@@ -0,0 +1,148 @@
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::game::{board, custom, grid, ui};
+use crate::game::{Bindings, Difficulty};
+use crate::storage;
+
+const FILE: &str = "settings.ron";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Preset {
+    Beginner,
+    Intermediate,
+    Expert,
+    Custom,
+}
+
+/// The last custom difficulty, kept while playing a preset.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CustomSettings {
+    pub columns: usize,
+    pub rows: usize,
+    pub mines: usize,
+}
+
+impl Default for CustomSettings {
+    fn default() -> Self {
+        let size = Difficulty::Intermediate.size();
+        Self {
+            columns: size.columns(),
+            rows: size.rows(),
+            mines: Difficulty::Intermediate.mines(),
+        }
+    }
+}
+
+/// Resource
+///
+/// Settings that are kept between runs, in the user's config directory.
+/// Anything missing from the file keeps its default.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    pub difficulty: Preset,
+    pub custom: CustomSettings,
+    pub tile_size: u32,
+    pub colors: ui::Colors,
+    pub question_marks: bool,
+    pub bindings: Bindings,
+    // name of the last new record
+    pub player_name: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            difficulty: Preset::Beginner,
+            custom: CustomSettings::default(),
+            tile_size: grid::TileSize::TEXTURE as u32,
+            colors: ui::Colors::default(),
+            question_marks: true,
+            bindings: Bindings::default(),
+            player_name: String::new(),
+        }
+    }
+}
+
+impl Settings {
+    #[inline]
+    fn path() -> Option<PathBuf> {
+        storage::config_file(FILE)
+    }
+
+    /// Read the settings file. When it's missing the defaults are used, when
+    /// it can't be read it's moved aside and the defaults are used with a
+    /// warning.
+    pub fn load() -> Self {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => { return Self::default(); }
+        };
+
+        match storage::read_ron::<Self>(&path) {
+            Ok(settings) => settings.unwrap_or_default(),
+            Err(err) => {
+                let backup = path.with_extension("ron.bak");
+                warn!("settings {} can't be read: {}, moved to {}", path.display(), err, backup.display());
+                let _ = fs::rename(&path, &backup);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or_else(|| "no config directory".to_string())?;
+        storage::write_ron(&path, self)
+    }
+
+    /// The difficulty to start with. A custom difficulty that doesn't fit
+    /// falls back to beginner.
+    pub fn difficulty(&self) -> Difficulty {
+        let custom = &self.custom;
+        let difficulty = match self.difficulty {
+            Preset::Beginner => { return Difficulty::Beginner; }
+            Preset::Intermediate => { return Difficulty::Intermediate; }
+            Preset::Expert => { return Difficulty::Expert; }
+            Preset::Custom => Difficulty::Custom {
+                size: grid::GridSize::new(custom.columns, custom.rows),
+                mines: custom.mines,
+            },
+        };
+
+        let limits = custom::CustomLimits::unbounded();
+        let valid = limits.check_columns(custom.columns)
+            .and_then(|_| limits.check_rows(custom.rows))
+            .and_then(|_| difficulty.validate(board::FirstClick::default()));
+        if let Err(err) = valid {
+            warn!("custom difficulty in settings: {}, using beginner", err);
+            return Difficulty::Beginner;
+        }
+        difficulty
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: &Difficulty) {
+        self.difficulty = match difficulty {
+            Difficulty::Beginner => Preset::Beginner,
+            Difficulty::Intermediate => Preset::Intermediate,
+            Difficulty::Expert => Preset::Expert,
+            Difficulty::Custom { size, mines } => {
+                self.custom = CustomSettings {
+                    columns: size.columns(),
+                    rows: size.rows(),
+                    mines: *mines,
+                };
+                Preset::Custom
+            }
+        };
+    }
+
+    /// Tile size, kept within the sizes the game can show.
+    #[inline]
+    pub fn tile_size(&self) -> grid::TileSize {
+        grid::TileSize::new(self.tile_size.clamp(grid::TileSize::MIN, grid::TileSize::MAX) as f32)
+    }
+}
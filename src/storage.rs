@@ -0,0 +1,74 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use ron::ser::PrettyConfig;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const APP_DIR: &str = "minesweeper";
+
+/// Path of a file in the user's config directory.
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR).join(name))
+}
+
+/// Path of a file in the user's data directory.
+pub fn data_file(name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_DIR).join(name))
+}
+
+/// Write the whole file or nothing: the contents go to a temporary file next
+/// to it first, which then replaces the file.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })
+}
+
+/// Add a line to the end of a file, the header goes first when the file is
+/// new.
+pub fn append_line(path: &Path, header: &str, line: &str) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "{}", header)?;
+    }
+    writeln!(file, "{}", line)
+}
+
+/// Read a RON file, none when there is no such file.
+pub fn read_ron<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => { return Ok(None); }
+        Err(err) => { return Err(err.to_string()); }
+    };
+    ron::from_str(&text)
+        .map(Some)
+        .map_err(|err| err.to_string())
+}
+
+pub fn write_ron<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let text = ron::ser::to_string_pretty(value, PrettyConfig::default())
+        .map_err(|err| err.to_string())?;
+    write_atomic(path, text.as_bytes())
+        .map_err(|err| format!("can't write {}: {}", path.display(), err))
+}
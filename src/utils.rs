@@ -1,3 +1,5 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use bevy::prelude::{Image, IVec2, TextureAtlas};
 use bevy::sprite::{Rect, SpriteSheetBundle, TextureAtlasSprite};
 use winit::dpi::PhysicalPosition;
@@ -19,6 +21,75 @@ pub fn center_window(window: &winit::window::Window) -> Option<IVec2> {
     return Some(position);
 }
 
+#[inline]
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// Today's date (UTC) as year-month-day.
+pub fn today() -> String {
+    let days = (unix_time() / 86400) as i64;
+
+    // days since 1970-01-01 to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Name for a file that is written now, unique as long as there's at most
+/// one per second.
+pub fn file_name(extension: &str) -> String {
+    format!("{}-{}.{}", today(), unix_time(), extension)
+}
+
+// URL-safe alphabet, so codes survive links and chat messages
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Base64 with the URL-safe alphabet and without padding.
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity((bytes.len() * 4).div_ceil(3));
+    for chunk in bytes.chunks(3) {
+        let value = chunk.iter().enumerate().fold(0u32, |value, (i, &byte)| value | (byte as u32) << (16 - i * 8));
+        for i in 0..=chunk.len() {
+            text.push(BASE64[(value >> (18 - i * 6)) as usize & 63] as char);
+        }
+    }
+    text
+}
+
+/// Bytes of a base64 text as written by `base64_encode`, none when it has
+/// anything else in it.
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if text.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    for chunk in text.chunks(4) {
+        let mut value = 0u32;
+        for (i, &ch) in chunk.iter().enumerate() {
+            let digit = BASE64.iter().position(|&b| b == ch)? as u32;
+            value |= digit << (18 - i * 6);
+        }
+        for i in 0..chunk.len() - 1 {
+            bytes.push((value >> (16 - i * 8)) as u8);
+        }
+    }
+    Some(bytes)
+}
+
 pub struct TextureAtlasSlicer<T: Copy> {
     rects: Vec<(T, Rect)>,
 }
@@ -60,15 +131,44 @@ impl<T: PartialEq> SpriteSheetBundleBuilder<T> {
     }
 
     pub fn get(&self, typ: T) -> Option<SpriteSheetBundle> {
+        let index = self.index(typ)?;
+        Some(SpriteSheetBundle {
+            sprite: TextureAtlasSprite::new(index),
+            texture_atlas: self.texture_atlas.clone(),
+            ..Default::default()
+        })
+    }
+
+    pub fn index(&self, typ: T) -> Option<usize> {
         for (t, index) in &self.indexes {
             if typ == *t {
-                return Some(SpriteSheetBundle {
-                    sprite: TextureAtlasSprite::new(*index),
-                    texture_atlas: self.texture_atlas.clone(),
-                    ..Default::default()
-                });
+                return Some(*index);
             }
         }
         None
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_round_trip() {
+        let bytes = [0xfb, 0xff, 0x00, 0x7f];
+        for len in 0..=bytes.len() {
+            let text = base64_encode(&bytes[..len]);
+            assert_eq!(text.len(), (len * 4).div_ceil(3));
+            assert_eq!(base64_decode(&text).as_deref(), Some(&bytes[..len]));
+        }
+        assert_eq!(base64_encode(&[0xfb, 0xff]), "-_8");
+    }
+
+    #[test]
+    fn base64_rejects_bad_input() {
+        assert_eq!(base64_decode("AAAAA"), None);
+        assert_eq!(base64_decode("AA=="), None);
+        assert_eq!(base64_decode("A+/A"), None);
+        assert_eq!(base64_decode("ab c"), None);
+    }
+}